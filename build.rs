@@ -12,15 +12,25 @@ fn main() {
     }
 }
 
+#[path = "src/enemy_types.rs"]
+mod enemy_types;
+
 mod tiled_export {
+    use super::enemy_types::EnemyType;
     use serde::Deserialize;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
     use std::fs::File;
     use std::io::{BufReader, BufWriter, Write};
 
     const COLLISION_TILE: i32 = 1;
     const KILL_TILE: i32 = 2;
     const WIN_TILE: i32 = 4;
+    const REFILL_TILE: i32 = 8;
+    const DOOR_TILE: i32 = 16;
+    const TOGGLE_SOLID_TILE: i32 = 32;
+    const TOGGLE_PASSABLE_TILE: i32 = 64;
+    const BREAKABLE_TILE: i32 = 128;
+    const BOUNCE_TILE: i32 = 256;
 
     pub fn export_tilemap(out_dir: &str) -> std::io::Result<()> {
         let filename = "map/tilemap.json";
@@ -43,12 +53,82 @@ mod tiled_export {
                         "Collision" => COLLISION_TILE,
                         "Kill" => KILL_TILE,
                         "Win" => WIN_TILE,
+                        "Refill" => REFILL_TILE,
+                        "Door" => DOOR_TILE,
+                        "Toggle Solid" => TOGGLE_SOLID_TILE,
+                        "Toggle Passable" => TOGGLE_PASSABLE_TILE,
+                        "Breakable" => BREAKABLE_TILE,
+                        "Bounce" => BOUNCE_TILE,
                         _ => 0,
                     },
                 )
             })
             .collect();
 
+        let bounce_tiles = tilemap
+            .tiles
+            .iter()
+            .filter(|tile| tile.tile_type == "Bounce")
+            .map(|tile| {
+                let velocity = tile.int_property("Bounce Velocity").unwrap_or_else(|| {
+                    panic!("Expected bounce tile {} to have a Bounce Velocity", tile.id)
+                });
+                format!("({}, {})", tile.id, velocity)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let surfaces = tilemap
+            .tiles
+            .iter()
+            .filter(|tile| {
+                tile.int_property("Friction").is_some()
+                    || tile.int_property("Conveyor Velocity").is_some()
+            })
+            .map(|tile| {
+                let mut fields = String::new();
+                if let Some(friction) = tile.int_property("Friction") {
+                    fields += &format!("friction: {}, ", friction);
+                }
+                if let Some(conveyor_velocity) = tile.int_property("Conveyor Velocity") {
+                    fields += &format!("conveyor_velocity: {}, ", conveyor_velocity);
+                }
+
+                format!(
+                    "({}, crate::Surface {{ {}..crate::Surface::NORMAL }})",
+                    tile.id, fields
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        // each solid toggle tile swaps with the passable one in the same place in the tileset order
+        let toggle_tiles_of_type = |tile_type: &str| {
+            tilemap
+                .tiles
+                .iter()
+                .filter(|tile| tile.tile_type == tile_type)
+                .map(|tile| tile.id)
+                .collect::<Vec<_>>()
+        };
+        let (toggle_solid, toggle_passable) = (
+            toggle_tiles_of_type("Toggle Solid"),
+            toggle_tiles_of_type("Toggle Passable"),
+        );
+        if toggle_solid.len() != toggle_passable.len() {
+            panic!(
+                "Expected as many Toggle Solid tiles as Toggle Passable ones, but there are {} and {}",
+                toggle_solid.len(),
+                toggle_passable.len()
+            );
+        }
+        let toggle_tiles = toggle_solid
+            .iter()
+            .zip(&toggle_passable)
+            .map(|(solid, passable)| format!("({}, {})", solid, passable))
+            .collect::<Vec<String>>()
+            .join(", ");
+
         let tile_info = (0..tilemap.tilecount)
             .map(|id| *tile_data.get(&id).unwrap_or(&0))
             .map(|tile_type| tile_type.to_string())
@@ -63,6 +143,24 @@ mod tiled_export {
 
         writeln!(&mut writer, "pub const KILL_TILE: i32 = {};", KILL_TILE)?;
         writeln!(&mut writer, "pub const WIN_TILE: i32 = {};", WIN_TILE)?;
+        writeln!(&mut writer, "pub const REFILL_TILE: i32 = {};", REFILL_TILE)?;
+        writeln!(&mut writer, "pub const DOOR_TILE: i32 = {};", DOOR_TILE)?;
+        writeln!(
+            &mut writer,
+            "pub const TOGGLE_SOLID_TILE: i32 = {};",
+            TOGGLE_SOLID_TILE
+        )?;
+        writeln!(
+            &mut writer,
+            "pub const BREAKABLE_TILE: i32 = {};",
+            BREAKABLE_TILE
+        )?;
+        writeln!(&mut writer, "pub const BOUNCE_TILE: i32 = {};", BOUNCE_TILE)?;
+        writeln!(
+            &mut writer,
+            "pub const BLANK_TILE: u16 = {};",
+            get_map_id(0)
+        )?;
 
         writeln!(
             &mut writer,
@@ -70,6 +168,27 @@ mod tiled_export {
             tile_info
         )?;
 
+        // the speed bounce tiles launch things at, in 64ths of a pixel per frame
+        writeln!(
+            &mut writer,
+            "pub const BOUNCE_VELOCITIES: &[(u16, i32)] = &[{}];",
+            bounce_tiles
+        )?;
+
+        // the solid and passable tiles of each kind of toggle block, which swap when a switch is hit
+        writeln!(
+            &mut writer,
+            "pub const TOGGLE_TILES: &[(u16, u16)] = &[{}];",
+            toggle_tiles
+        )?;
+
+        // tiles which change how the wizard moves while standing on them
+        writeln!(
+            &mut writer,
+            "pub const SURFACES: &[(u16, crate::Surface)] = &[{}];",
+            surfaces
+        )?;
+
         Ok(())
     }
 
@@ -111,32 +230,106 @@ mod tiled_export {
             .as_ref()
             .expect("Expected third layer to be an object layer")
             .iter()
-            .map(|object| (&object.object_type, (object.x, object.y)));
-        let mut snails = vec![];
-        let mut slimes = vec![];
+            .map(|object| {
+                (
+                    &object.object_type,
+                    (object.x, object.y),
+                    (object.width, object.height),
+                    object,
+                )
+            });
+        let mut enemies = vec![];
         let mut enemy_stops = vec![];
+        let mut checkpoints = vec![];
+        let mut collectibles = vec![];
+        let mut keys = vec![];
+        let mut switches = vec![];
+        let mut springs = vec![];
+        let mut water = vec![];
+        let mut wind = vec![];
+        let mut teleporters = vec![];
         let mut player_start = None;
 
-        for (object_type, (x, y)) in objects {
+        for (object_type, (x, y), (width, height), object) in objects {
+            if let Some(enemy_type) = EnemyType::from_object_type(object_type) {
+                // enemies which patrol follow a polyline drawn in Tiled, starting at its first
+                // point. The points are relative to the position of the object.
+                let path = object
+                    .polyline
+                    .iter()
+                    .flatten()
+                    .map(|point| (x + point.x.round() as i32, y + point.y.round() as i32))
+                    .collect::<Vec<_>>();
+                if enemy_type == EnemyType::Patrol && path.len() < 2 {
+                    panic!(
+                        "Patrol at ({}, {}) needs a polyline with at least two points",
+                        x, y
+                    );
+                }
+                let position = path.first().copied().unwrap_or((x, y));
+                let ping_pong = object.bool_property("Ping Pong").unwrap_or(false);
+
+                enemies.push((enemy_type, position, path, ping_pong));
+                continue;
+            }
+
             match object_type.as_str() {
-                "Snail Spawn" => snails.push((x, y)),
-                "Slime Spawn" => slimes.push((x, y)),
                 "Player Start" => player_start = Some((x, y)),
                 "Enemy Stop" => enemy_stops.push((x, y)),
+                "Checkpoint" => checkpoints.push((x, y)),
+                "Collectible" => collectibles.push((x, y)),
+                "Key" => keys.push((x, y)),
+                "Switch" => switches.push((x, y)),
+                "Spring" => springs.push((x, y)),
+                "Water" => water.push((x, y, width, height)),
+                "Wind" => {
+                    let force = (
+                        object.int_property("Wind X").unwrap_or(0),
+                        object.int_property("Wind Y").unwrap_or(0),
+                    );
+                    wind.push((x, y, width, height, force));
+                }
+                "Teleporter" => {
+                    let pair = object.int_property("Pair").unwrap_or(0);
+                    teleporters.push((pair, (x, y)));
+                }
                 _ => panic!("Unknown object type {}", object_type),
             }
         }
 
         let player_start = player_start.expect("Need a start place for the player");
 
-        let slimes_str = slimes
+        // teleporters with the same pair number are linked to each other
+        let mut teleporter_groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (pair, position) in teleporters {
+            teleporter_groups.entry(pair).or_default().push(position);
+        }
+        let teleporter_pairs = teleporter_groups
             .iter()
-            .map(|slime| format!("({}, {})", slime.0, slime.1))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let snails_str = snails
+            .map(|(pair, ends)| match ends[..] {
+                [from, to] => (from, to),
+                _ => panic!(
+                    "Teleporter pair {} needs exactly two teleporters, but has {}",
+                    pair,
+                    ends.len()
+                ),
+            })
+            .collect::<Vec<_>>();
+
+        let enemies_str = enemies
             .iter()
-            .map(|slime| format!("({}, {})", slime.0, slime.1))
+            .map(|(enemy, position, path, ping_pong)| {
+                let path = path
+                    .iter()
+                    .map(|point| format!("({}, {})", point.0, point.1))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!(
+                    "crate::enemies::EnemySpawn {{ enemy_type: crate::enemy_types::EnemyType::{:?}, position: ({}, {}), path: &[{}], ping_pong: {} }}",
+                    enemy, position.0, position.1, path, ping_pong
+                )
+            })
             .collect::<Vec<_>>()
             .join(", ");
         let enemy_stop_str = enemy_stops
@@ -144,21 +337,97 @@ mod tiled_export {
             .map(|enemy_stop| format!("({}, {})", enemy_stop.0, enemy_stop.1))
             .collect::<Vec<_>>()
             .join(", ");
+        let checkpoints_str = checkpoints
+            .iter()
+            .map(|checkpoint| format!("({}, {})", checkpoint.0, checkpoint.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let collectibles_str = collectibles
+            .iter()
+            .map(|collectible| format!("({}, {})", collectible.0, collectible.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let keys_str = keys
+            .iter()
+            .map(|key| format!("({}, {})", key.0, key.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let switches_str = switches
+            .iter()
+            .map(|switch| format!("({}, {})", switch.0, switch.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let springs_str = springs
+            .iter()
+            .map(|spring| format!("({}, {})", spring.0, spring.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let water_str = water
+            .iter()
+            .map(|water| format!("({}, {}, {}, {})", water.0, water.1, water.2, water.3))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let teleporters_str = teleporter_pairs
+            .iter()
+            .map(|(from, to)| format!("(({}, {}), ({}, {}))", from.0, from.1, to.0, to.1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let wind_str = wind
+            .iter()
+            .map(|wind| {
+                format!(
+                    "(({}, {}, {}, {}), ({}, {}))",
+                    wind.0, wind.1, wind.2, wind.3, wind.4 .0, wind.4 .1
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
 
         writeln!(
             &mut writer,
-            "const SNAILS: &[(i32, i32)] = &[{}];",
-            snails_str
+            "const ENEMIES: &[crate::enemies::EnemySpawn] = &[{}];",
+            enemies_str
         )?;
         writeln!(
             &mut writer,
-            "const SLIMES: &[(i32, i32)] = &[{}];",
-            slimes_str
+            "const ENEMY_STOPS: &[(i32, i32)] = &[{}];",
+            enemy_stop_str
         )?;
         writeln!(
             &mut writer,
-            "const ENEMY_STOPS: &[(i32, i32)] = &[{}];",
-            enemy_stop_str
+            "const CHECKPOINTS: &[(i32, i32)] = &[{}];",
+            checkpoints_str
+        )?;
+        writeln!(
+            &mut writer,
+            "const COLLECTIBLES: &[(i32, i32)] = &[{}];",
+            collectibles_str
+        )?;
+        writeln!(&mut writer, "const KEYS: &[(i32, i32)] = &[{}];", keys_str)?;
+        writeln!(
+            &mut writer,
+            "const SWITCHES: &[(i32, i32)] = &[{}];",
+            switches_str
+        )?;
+        writeln!(
+            &mut writer,
+            "const SPRINGS: &[(i32, i32)] = &[{}];",
+            springs_str
+        )?;
+        writeln!(
+            &mut writer,
+            "const WATER: &[crate::Area] = &[{}];",
+            water_str
+        )?;
+        writeln!(
+            &mut writer,
+            "const WIND: &[(crate::Area, (i32, i32))] = &[{}];",
+            wind_str
+        )?;
+        writeln!(
+            &mut writer,
+            "const TELEPORTERS: &[crate::TeleporterPair] = &[{}];",
+            teleporters_str
         )?;
         writeln!(
             &mut writer,
@@ -166,13 +435,26 @@ mod tiled_export {
             player_start.0, player_start.1
         )?;
 
+        let wall_resets_recalls = level.bool_property("Wall Resets Recalls").unwrap_or(false);
+        let hat_bounces = level.int_property("Hat Bounces").unwrap_or(0);
+        let hat_platform = level.bool_property("Hat Platform").unwrap_or(false);
+        let max_recalls = level.int_property("Recalls").unwrap_or(2);
+        let charged_throws = level.bool_property("Charged Throws").unwrap_or(false);
+        let aim_preview = level.bool_property("Aim Preview").unwrap_or(false);
+        let hat_teleports = level.bool_property("Hat Teleports").unwrap_or(false);
+        let recall_refill = match level.string_property("Recall Refill").unwrap_or("Landing") {
+            "Landing" => "Landing",
+            "Refill Tiles" => "RefillTiles",
+            refill => panic!("Unknown recall refill {}", refill),
+        };
+
         writeln!(
             &mut writer,
             r#"
             use crate::Level;
             use agb::number::Vector2D;
 
-            pub const fn get_level() -> Level {{
+            pub const fn get_level() -> Level<'static> {{
                 Level {{
                     background: &TILEMAP,
                     foreground: &BACKGROUND,
@@ -180,12 +462,38 @@ mod tiled_export {
                     collision: &crate::map_tiles::tilemap::TILE_DATA,
     
                     enemy_stops: &ENEMY_STOPS,
-                    slimes: &SLIMES,
-                    snails: &SNAILS,
+                    enemies: &ENEMIES,
+                    checkpoints: &CHECKPOINTS,
+                    collectibles: &COLLECTIBLES,
+                    keys: &KEYS,
+                    switches: &SWITCHES,
+                    springs: &SPRINGS,
+                    water: &WATER,
+                    wind: &WIND,
+                    teleporters: &TELEPORTERS,
                     start_pos: START_POS,
+
+                    rules: crate::LevelRules {{
+                        wall_resets_recalls: {wall_resets_recalls},
+                        hat_bounces: {hat_bounces},
+                        hat_platform: {hat_platform},
+                        max_recalls: {max_recalls},
+                        recall_refill: crate::RecallRefill::{recall_refill},
+                        charged_throws: {charged_throws},
+                        aim_preview: {aim_preview},
+                        hat_teleports: {hat_teleports},
+                    }},
                 }}
             }}
-            "#
+            "#,
+            wall_resets_recalls = wall_resets_recalls,
+            hat_bounces = hat_bounces,
+            hat_platform = hat_platform,
+            max_recalls = max_recalls,
+            recall_refill = recall_refill,
+            charged_throws = charged_throws,
+            aim_preview = aim_preview,
+            hat_teleports = hat_teleports,
         )?;
 
         Ok(())
@@ -203,6 +511,13 @@ mod tiled_export {
         layers: Vec<TiledLayer>,
         width: i32,
         height: i32,
+        properties: Option<Vec<TiledProperty>>,
+    }
+
+    #[derive(Deserialize)]
+    struct TiledProperty {
+        name: String,
+        value: serde_json::Value,
     }
 
     #[derive(Deserialize)]
@@ -217,6 +532,18 @@ mod tiled_export {
         object_type: String,
         x: i32,
         y: i32,
+        #[serde(default)]
+        width: i32,
+        #[serde(default)]
+        height: i32,
+        properties: Option<Vec<TiledProperty>>,
+        polyline: Option<Vec<TiledPoint>>,
+    }
+
+    #[derive(Deserialize)]
+    struct TiledPoint {
+        x: f64,
+        y: f64,
     }
 
     #[derive(Deserialize)]
@@ -230,5 +557,61 @@ mod tiled_export {
         id: i32,
         #[serde(rename = "type")]
         tile_type: String,
+        properties: Option<Vec<TiledProperty>>,
+    }
+
+    // levels, tiles and objects can all have custom properties set in Tiled
+    trait WithProperties {
+        fn properties(&self) -> &Option<Vec<TiledProperty>>;
+
+        fn property(&self, name: &str) -> Option<&serde_json::Value> {
+            self.properties()
+                .iter()
+                .flatten()
+                .find(|property| property.name == name)
+                .map(|property| &property.value)
+        }
+
+        fn bool_property(&self, name: &str) -> Option<bool> {
+            self.property(name).map(|value| {
+                value
+                    .as_bool()
+                    .unwrap_or_else(|| panic!("Expected property {} to be a bool", name))
+            })
+        }
+
+        fn int_property(&self, name: &str) -> Option<i64> {
+            self.property(name).map(|value| {
+                value
+                    .as_i64()
+                    .unwrap_or_else(|| panic!("Expected property {} to be an int", name))
+            })
+        }
+
+        fn string_property(&self, name: &str) -> Option<&str> {
+            self.property(name).map(|value| {
+                value
+                    .as_str()
+                    .unwrap_or_else(|| panic!("Expected property {} to be a string", name))
+            })
+        }
+    }
+
+    impl WithProperties for TiledLevel {
+        fn properties(&self) -> &Option<Vec<TiledProperty>> {
+            &self.properties
+        }
+    }
+
+    impl WithProperties for TiledTile {
+        fn properties(&self) -> &Option<Vec<TiledProperty>> {
+            &self.properties
+        }
+    }
+
+    impl WithProperties for TiledObject {
+        fn properties(&self) -> &Option<Vec<TiledProperty>> {
+            &self.properties
+        }
     }
 }
@@ -1,9 +1,9 @@
 <?xml version="1.0" encoding="UTF-8"?>
-<tileset version="1.5" tiledversion="1.7.0" name="joined_together_in_a_tilemap-Sheet" tilewidth="8" tileheight="8" tilecount="364" columns="28">
+<tileset version="1.5" tiledversion="1.7.0" name="joined_together_in_a_tilemap-Sheet" tilewidth="8" tileheight="8" tilecount="420" columns="28">
  <editorsettings>
   <export target="tilemap.json" format="json"/>
  </editorsettings>
- <image source="../gfx/tile_sheet.png" trans="2ce8f4" width="224" height="104"/>
+ <image source="../gfx/tile_sheet.png" trans="2ce8f4" width="224" height="120"/>
  <tile id="8" type="Collision"/>
  <tile id="9" type="Kill"/>
  <tile id="36" type="Collision"/>
@@ -99,4 +99,35 @@
  <tile id="331" type="Kill"/>
  <tile id="354" type="Collision"/>
  <tile id="355" type="Collision"/>
+ <tile id="392" type="Refill"/>
+ <tile id="395" type="Door"/>
+ <tile id="396" type="Door"/>
+ <tile id="397" type="Toggle Solid"/>
+ <tile id="398" type="Toggle Passable"/>
+ <tile id="399" type="Breakable"/>
+ <tile id="400" type="Bounce">
+  <properties>
+   <property name="Bounce Velocity" type="int" value="160"/>
+  </properties>
+ </tile>
+ <tile id="401" type="Bounce">
+  <properties>
+   <property name="Bounce Velocity" type="int" value="224"/>
+  </properties>
+ </tile>
+ <tile id="402" type="Collision">
+  <properties>
+   <property name="Friction" type="int" value="62"/>
+  </properties>
+ </tile>
+ <tile id="403" type="Collision">
+  <properties>
+   <property name="Conveyor Velocity" type="int" value="-48"/>
+  </properties>
+ </tile>
+ <tile id="404" type="Collision">
+  <properties>
+   <property name="Conveyor Velocity" type="int" value="48"/>
+  </properties>
+ </tile>
 </tileset>
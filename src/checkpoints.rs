@@ -0,0 +1,83 @@
+use super::{object_tiles, sfx::SfxPlayer, Entity, FixedNumberType};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+enum CheckpointState {
+    Inactive,
+    Raising(i32), // start frame
+    Active,
+}
+
+pub struct Checkpoint<'a> {
+    entity: Entity<'a>,
+    state: CheckpointState,
+}
+
+impl<'a> Checkpoint<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::CHECKPOINT_INACTIVE);
+
+        Checkpoint {
+            entity,
+            state: CheckpointState::Inactive,
+        }
+    }
+
+    pub fn position(&self) -> Vector2D<FixedNumberType> {
+        self.entity.position
+    }
+
+    // returns true if the player has just activated this checkpoint
+    pub fn update(
+        &mut self,
+        player_pos: Vector2D<FixedNumberType>,
+        timer: i32,
+        sfx_player: &mut SfxPlayer,
+    ) -> bool {
+        match self.state {
+            CheckpointState::Inactive => {
+                self.entity
+                    .sprite
+                    .set_tile_id(object_tiles::CHECKPOINT_INACTIVE);
+
+                if self.entity.is_within(player_pos, 12) {
+                    self.state = CheckpointState::Raising(timer);
+                    sfx_player.checkpoint();
+                    return true;
+                }
+            }
+            CheckpointState::Raising(start_frame) => {
+                let offset = (timer - start_frame) / 4;
+                if offset >= 3 {
+                    self.state = CheckpointState::Active;
+                } else {
+                    self.entity
+                        .sprite
+                        .set_tile_id(object_tiles::CHECKPOINT_RAISE_START + (offset * 4) as u16);
+                }
+            }
+            CheckpointState::Active => {
+                let offset = timer / 16 % 2;
+                self.entity
+                    .sprite
+                    .set_tile_id(object_tiles::CHECKPOINT_ACTIVE_START + (offset * 4) as u16);
+            }
+        }
+
+        false
+    }
+
+    pub fn deactivate(&mut self) {
+        self.state = CheckpointState::Inactive;
+    }
+
+    // the level timer starts again from 0 on restart, so finish any animation which depends on it
+    pub fn restart(&mut self) {
+        if let CheckpointState::Raising(_) = self.state {
+            self.state = CheckpointState::Active;
+        }
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
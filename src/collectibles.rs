@@ -0,0 +1,41 @@
+use super::{object_tiles, sfx::SfxPlayer, Entity, FixedNumberType};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+pub struct Collectible<'a> {
+    entity: Entity<'a>,
+}
+
+impl<'a> Collectible<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::COLLECTIBLE_START);
+
+        Collectible { entity }
+    }
+
+    // returns true if the wizard or their hat has just picked this up
+    pub fn update(
+        &mut self,
+        player_pos: Vector2D<FixedNumberType>,
+        hat_pos: Vector2D<FixedNumberType>,
+        timer: i32,
+        sfx_player: &mut SfxPlayer,
+    ) -> bool {
+        let offset = (timer / 8 % 4) as u16;
+        self.entity
+            .sprite
+            .set_tile_id(object_tiles::COLLECTIBLE_START + offset * 4);
+
+        let touches = |position: Vector2D<FixedNumberType>| self.entity.is_within(position, 12);
+
+        if touches(player_pos) || touches(hat_pos) {
+            sfx_player.collect();
+            return true;
+        }
+
+        false
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
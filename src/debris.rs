@@ -0,0 +1,39 @@
+use super::{object_tiles, Entity, FixedNumberType};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+const FRAMES: i32 = 4;
+const FRAME_LENGTH: i32 = 4;
+
+// the pieces of a breakable block flying apart after it was smashed
+pub struct Debris<'a> {
+    entity: Entity<'a>,
+    start_frame: i32,
+}
+
+impl<'a> Debris<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>, timer: i32) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::DEBRIS_START);
+
+        Debris {
+            entity,
+            start_frame: timer,
+        }
+    }
+
+    // returns false once the animation has finished
+    pub fn update(&mut self, timer: i32) -> bool {
+        let frame = (timer - self.start_frame) / FRAME_LENGTH;
+        if !(0..FRAMES).contains(&frame) {
+            return false;
+        }
+
+        self.entity
+            .sprite
+            .set_tile_id(object_tiles::DEBRIS_START + frame as u16 * 4);
+        true
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
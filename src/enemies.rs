@@ -1,85 +1,60 @@
-use super::{object_tiles, sfx::SfxPlayer, Entity, FixedNumberType, HatState, Level};
+use super::{
+    enemy_types::EnemyType, jump_tuning, object_tiles, sfx::SfxPlayer, sixty_fourths, Entity,
+    FixedNumberType, HatState, Level,
+};
 use agb::{
     display::object::{ObjectControl, Size},
     number::Vector2D,
 };
+use alloc::boxed::Box;
 
-enum UpdateState {
+pub enum UpdateState {
     Nothing,
     KillPlayer,
     Remove,
+    Fire(Vector2D<FixedNumberType>, Vector2D<FixedNumberType>), // position and velocity
 }
 
-pub enum Enemy<'a> {
-    Slime(Slime<'a>),
-    Snail(Snail<'a>),
-    Empty,
-}
-
-impl<'a> Default for Enemy<'a> {
-    fn default() -> Self {
-        Enemy::Empty
-    }
-}
-
-pub enum EnemyUpdateState {
-    None,
-    KillPlayer,
-}
-
-impl<'a> Enemy<'a> {
-    pub fn is_empty(&self) -> bool {
-        match self {
-            Enemy::Empty => true,
-            _ => false,
-        }
-    }
-
-    pub fn new_slime(object: &'a ObjectControl, start_pos: Vector2D<FixedNumberType>) -> Self {
-        Enemy::Slime(Slime::new(object, start_pos + (0, 1).into()))
-    }
-
-    pub fn new_snail(object: &'a ObjectControl, start_pos: Vector2D<FixedNumberType>) -> Self {
-        Enemy::Snail(Snail::new(object, start_pos))
-    }
-
-    pub fn collides_with_hat(&self, position: Vector2D<FixedNumberType>) -> bool {
-        match self {
-            Enemy::Snail(snail) => snail.collides_with(position),
-            _ => false,
-        }
-    }
-
-    pub fn update(
+// How a kind of enemy behaves. Each kind of enemy implements this, and is spawned from its
+// variant of `EnemyType` so it can be placed in levels.
+pub trait EnemyBehaviour {
+    fn update(
         &mut self,
         level: &Level,
         player_pos: Vector2D<FixedNumberType>,
         hat_state: HatState,
         timer: i32,
         sfx_player: &mut SfxPlayer,
-    ) -> EnemyUpdateState {
-        let update_state = match self {
-            Enemy::Slime(slime) => slime.update(level, player_pos, hat_state, timer, sfx_player),
-            Enemy::Snail(snail) => snail.update(level, player_pos, hat_state, timer, sfx_player),
-            Enemy::Empty => UpdateState::Nothing,
-        };
+    ) -> UpdateState;
 
-        match update_state {
-            UpdateState::Remove => {
-                *self = Enemy::Empty;
-                EnemyUpdateState::None
-            }
-            UpdateState::KillPlayer => EnemyUpdateState::KillPlayer,
-            UpdateState::Nothing => EnemyUpdateState::None,
-        }
+    fn commit(&mut self, background_offset: Vector2D<FixedNumberType>);
+
+    // does the thrown hat bounce off this enemy
+    fn collides_with_hat(&self, _position: Vector2D<FixedNumberType>) -> bool {
+        false
     }
+}
 
-    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
-        match self {
-            Enemy::Slime(slime) => slime.commit(background_offset),
-            Enemy::Snail(snail) => snail.commit(background_offset),
-            Enemy::Empty => {}
-        }
+pub type Enemy<'a> = Box<dyn EnemyBehaviour + 'a>;
+
+// An object in Tiled which places an enemy in the level
+pub struct EnemySpawn {
+    pub enemy_type: EnemyType,
+    pub position: (i32, i32),
+    pub path: &'static [(i32, i32)], // the points of the polyline for enemies which patrol
+    pub ping_pong: bool,             // does the patrol go back and forth rather than loop
+}
+
+pub fn spawn<'a>(object: &'a ObjectControl, spawn: &'static EnemySpawn) -> Enemy<'a> {
+    match spawn.enemy_type {
+        EnemyType::Slime => Box::new(Slime::new(
+            object,
+            Vector2D::from(spawn.position) + (0, 1).into(),
+        )),
+        EnemyType::Snail => Box::new(Snail::new(object, spawn.position.into())),
+        EnemyType::Bat => Box::new(Bat::new(object, spawn.position.into())),
+        EnemyType::Turret => Box::new(Turret::new(object, spawn.position.into())),
+        EnemyType::Patrol => Box::new(Patroller::new(object, spawn.path, spawn.ping_pong)),
     }
 }
 
@@ -101,16 +76,61 @@ impl<'a> EnemyInfo<'a> {
     }
 
     fn update(&mut self, level: &Level) {
+        // enemy stops can be placed to keep enemies in a smaller area than the ground they're on
         for &enemy_stop in level.enemy_stops {
             if (self.entity.position + self.entity.velocity - enemy_stop.into())
                 .manhattan_distance()
                 < 8.into()
             {
-                self.entity.velocity = (0, 0).into();
+                self.entity.velocity.x = 0.into();
             }
         }
 
-        self.entity.update_position(level);
+        // don't walk into walls or off the edges of platforms
+        let distance = self.entity.velocity.x;
+        if self.is_on_ground(level)
+            && (self.wall_ahead(level, distance) || self.ledge_ahead(level, distance))
+        {
+            self.entity.velocity.x = 0.into();
+        }
+
+        // enemies fall the same way the wizard does
+        self.entity.velocity.y += sixty_fourths(jump_tuning::GRAVITY);
+        self.entity.velocity = self.entity.update_position(level);
+    }
+
+    fn is_on_ground(&self, level: &Level) -> bool {
+        self.entity.is_on_ground(level)
+    }
+
+    // enemies only notice the wizard when they're close and there's nothing solid in the way
+    fn notices(&self, level: &Level, player_pos: Vector2D<FixedNumberType>, range: i32) -> bool {
+        self.entity.is_within(player_pos, range)
+            && level.has_line_of_sight(self.entity.position, player_pos)
+    }
+
+    fn wall_ahead(&self, level: &Level, distance: FixedNumberType) -> bool {
+        distance != 0.into()
+            && self
+                .entity
+                .collision_at_point(level, self.entity.position + (distance, 0.into()).into())
+    }
+
+    // is there no ground under the front of the enemy once it has moved this far
+    fn ledge_ahead(&self, level: &Level, distance: FixedNumberType) -> bool {
+        if distance == 0.into() {
+            return false;
+        }
+
+        let half_width = self.entity.collision_mask.x as i32 / 2;
+        let front = if distance > 0.into() {
+            self.entity.position.x + distance + half_width - 1
+        } else {
+            self.entity.position.x + distance - half_width
+        };
+        let below = self.entity.position.y + self.entity.collision_mask.y as i32 / 2;
+
+        !level.collides(front.floor() / 8, below.floor() / 8)
     }
 
     fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
@@ -118,6 +138,11 @@ impl<'a> EnemyInfo<'a> {
     }
 }
 
+// upward velocity the slime jumps with in 64ths of a pixel per frame, so that it lands again at the
+// end of the jumping animation SLIME_JUMP_FRAMES later
+const SLIME_JUMP_VELOCITY: i32 = 56;
+const SLIME_JUMP_FRAMES: i32 = 28;
+
 enum SlimeState {
     Idle,
     Jumping(i32), // the start frame of the jumping animation
@@ -140,7 +165,9 @@ impl<'a> Slime<'a> {
 
         slime
     }
+}
 
+impl<'a> EnemyBehaviour for Slime<'a> {
     fn update(
         &mut self,
         level: &Level,
@@ -149,8 +176,7 @@ impl<'a> Slime<'a> {
         timer: i32,
         sfx_player: &mut SfxPlayer,
     ) -> UpdateState {
-        let player_has_collided =
-            (self.enemy_info.entity.position - player_pos).magnitude_squared() < (10 * 10).into();
+        let player_has_collided = self.enemy_info.entity.is_within(player_pos, 10);
 
         match self.state {
             SlimeState::Idle => {
@@ -160,20 +186,24 @@ impl<'a> Slime<'a> {
                     .sprite
                     .set_tile_id(object_tiles::SLIME_IDLE_START + offset as u16);
 
-                if (self.enemy_info.entity.position - player_pos).magnitude_squared()
-                    < (64 * 64).into()
+                let x_vel: FixedNumberType = if self.enemy_info.entity.position.x > player_pos.x {
+                    -1
+                } else {
+                    1
+                }
+                .into();
+
+                // slimes won't jump if they would land off the edge of their platform
+                let jump_distance = x_vel / 4 * SLIME_JUMP_FRAMES;
+
+                if self.enemy_info.notices(level, player_pos, 64)
+                    && self.enemy_info.is_on_ground(level)
+                    && !self.enemy_info.ledge_ahead(level, jump_distance)
                 {
                     self.state = SlimeState::Jumping(timer);
 
-                    let x_vel: FixedNumberType =
-                        if self.enemy_info.entity.position.x > player_pos.x {
-                            -1
-                        } else {
-                            1
-                        }
-                        .into();
-
-                    self.enemy_info.entity.velocity = (x_vel / 4, 0.into()).into();
+                    self.enemy_info.entity.velocity =
+                        (x_vel / 4, -sixty_fourths(SLIME_JUMP_VELOCITY)).into();
                 }
 
                 if player_has_collided {
@@ -191,11 +221,17 @@ impl<'a> Slime<'a> {
                     sfx_player.slime_jump();
                 }
 
-                if offset >= 7 {
-                    self.enemy_info.entity.velocity = (0, 0).into();
+                // the jump lasts until the slime lands again, even if it takes longer than the
+                // animation
+                if offset >= 7 && self.enemy_info.is_on_ground(level) {
+                    self.enemy_info.entity.velocity.x = 0.into();
                     self.state = SlimeState::Idle;
                 } else {
-                    let sprite_offset = if offset >= 4 { 7 - offset } else { offset };
+                    let sprite_offset = if offset >= 4 {
+                        (7 - offset).max(0)
+                    } else {
+                        offset
+                    };
 
                     self.enemy_info
                         .entity
@@ -217,7 +253,7 @@ impl<'a> Slime<'a> {
                 }
 
                 let offset = (timer - dying_start_frame) / 4;
-                self.enemy_info.entity.velocity = (0, 0).into();
+                self.enemy_info.entity.velocity.x = 0.into();
 
                 if offset >= 4 {
                     return UpdateState::Remove;
@@ -264,9 +300,11 @@ impl<'a> Snail<'a> {
 
         snail
     }
+}
 
-    pub fn collides_with(&self, position: Vector2D<FixedNumberType>) -> bool {
-        (self.enemy_info.entity.position - position).magnitude_squared() < (15 * 15).into()
+impl<'a> EnemyBehaviour for Snail<'a> {
+    fn collides_with_hat(&self, position: Vector2D<FixedNumberType>) -> bool {
+        self.enemy_info.entity.is_within(position, 15)
     }
 
     fn update(
@@ -277,18 +315,15 @@ impl<'a> Snail<'a> {
         timer: i32,
         sfx_player: &mut SfxPlayer,
     ) -> UpdateState {
-        let player_has_collided =
-            (self.enemy_info.entity.position - player_pos).magnitude_squared() < (10 * 10).into();
+        let player_has_collided = self.enemy_info.entity.is_within(player_pos, 10);
 
         match self.state {
             SnailState::Idle(wait_time) => {
-                self.enemy_info.entity.velocity = (0, 0).into();
+                self.enemy_info.entity.velocity.x = 0.into();
 
                 if wait_time == 0 || timer - wait_time > 120 {
                     // wait at least 2 seconds after switching to this state
-                    if (self.enemy_info.entity.position - player_pos).magnitude_squared()
-                        < (48 * 48).into()
-                    {
+                    if self.enemy_info.notices(level, player_pos, 48) {
                         // player is close
                         self.state = SnailState::Emerging(timer);
                         sfx_player.snail_emerge();
@@ -313,7 +348,7 @@ impl<'a> Snail<'a> {
                 if offset >= 5 {
                     self.state = SnailState::Moving(timer);
                 }
-                self.enemy_info.entity.velocity = (0, 0).into();
+                self.enemy_info.entity.velocity.x = 0.into();
 
                 self.enemy_info
                     .entity
@@ -353,7 +388,7 @@ impl<'a> Snail<'a> {
                         }
                         .into();
 
-                    self.enemy_info.entity.velocity = (x_vel / 8, 0.into()).into();
+                    self.enemy_info.entity.velocity.x = x_vel / 8;
                 }
 
                 if player_has_collided {
@@ -375,7 +410,7 @@ impl<'a> Snail<'a> {
                     .entity
                     .sprite
                     .set_tile_id(object_tiles::SNAIL_EMERGE_START + (offset * 4) as u16);
-                self.enemy_info.entity.velocity = (0, 0).into();
+                self.enemy_info.entity.velocity.x = 0.into();
 
                 if player_has_collided {
                     if hat_state != HatState::WizardTowards {
@@ -402,12 +437,406 @@ impl<'a> Snail<'a> {
                 };
 
                 self.enemy_info.entity.sprite.set_tile_id(tile_id);
+                self.enemy_info.entity.velocity.x = 0.into();
+            }
+        }
+
+        self.enemy_info.update(level);
+
+        UpdateState::Nothing
+    }
+
+    fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.enemy_info.commit(background_offset);
+    }
+}
+
+// how far a patroller moves along its path each frame
+const PATROL_SPEED: i32 = 32; // in 64ths of a pixel
+
+// A spiked ball which follows a path drawn in Tiled. It can't be killed, so the wizard has to time
+// getting past it.
+pub struct Patroller<'a> {
+    enemy_info: EnemyInfo<'a>,
+    path: &'static [(i32, i32)],
+    ping_pong: bool,
+    target: usize,       // the point on the path it is currently heading to
+    going_forward: bool, // only false while going back along a ping pong path
+}
+
+impl<'a> Patroller<'a> {
+    fn new(object: &'a ObjectControl, path: &'static [(i32, i32)], ping_pong: bool) -> Self {
+        // build.rs makes sure every patrol has a path with at least two points
+        let start_pos = path[0].into();
+        let mut patroller = Patroller {
+            enemy_info: EnemyInfo::new(object, start_pos, (14u16, 14u16).into()),
+            path,
+            ping_pong,
+            target: 0,
+            going_forward: true,
+        };
+
+        patroller
+            .enemy_info
+            .entity
+            .sprite
+            .set_sprite_size(Size::S16x16);
+
+        patroller
+    }
+
+    fn next_target(&mut self) {
+        let last = self.path.len() - 1;
+        if !self.ping_pong {
+            self.target = if self.target == last {
+                0
+            } else {
+                self.target + 1
+            };
+            return;
+        }
+
+        if self.going_forward && self.target == last {
+            self.going_forward = false;
+        } else if !self.going_forward && self.target == 0 {
+            self.going_forward = true;
+        }
+
+        if self.going_forward {
+            self.target += 1;
+        } else {
+            self.target -= 1;
+        }
+    }
+}
+
+impl<'a> EnemyBehaviour for Patroller<'a> {
+    fn collides_with_hat(&self, position: Vector2D<FixedNumberType>) -> bool {
+        self.enemy_info.entity.is_within(position, 12)
+    }
+
+    fn update(
+        &mut self,
+        _level: &Level,
+        player_pos: Vector2D<FixedNumberType>,
+        _hat_state: HatState,
+        timer: i32,
+        _sfx_player: &mut SfxPlayer,
+    ) -> UpdateState {
+        let offset = (timer / 8 % 2) as u16;
+        self.enemy_info
+            .entity
+            .sprite
+            .set_tile_id(object_tiles::PATROL_START + offset * 4);
+
+        // patrollers fly along their path rather than moving under gravity, so go through walls
+        let mut position = self.enemy_info.entity.position;
+        let mut remaining = sixty_fourths(PATROL_SPEED);
+        // bounded in case the path has points on top of each other
+        for _ in 0..self.path.len() * 2 {
+            let target: Vector2D<FixedNumberType> = self.path[self.target].into();
+            let to_target = target - position;
+            let distance = to_target.magnitude();
+
+            if distance > remaining {
+                position += to_target / distance * remaining;
+                break;
+            }
+
+            position = target;
+            remaining -= distance;
+            self.next_target();
+        }
+        self.enemy_info.entity.position = position;
+
+        if (position - player_pos).magnitude_squared() < (10 * 10).into() {
+            return UpdateState::KillPlayer;
+        }
+
+        UpdateState::Nothing
+    }
+
+    fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.enemy_info.commit(background_offset);
+    }
+}
+
+// Bat tuning, distances in pixels and velocities in 64ths of a pixel per frame
+mod bat_tuning {
+    // bats fly in a figure of eight around where they were placed
+    pub const SWOOP_PERIOD: i32 = 128;
+    pub const SWOOP_WIDTH: i32 = 24;
+    pub const SWOOP_HEIGHT: i32 = 8;
+
+    // and dive at the wizard when they're close enough underneath
+    pub const DIVE_RANGE: i32 = 64;
+    pub const DIVE_VELOCITY: i32 = 96;
+    pub const DIVE_FRAMES: i32 = 40;
+    pub const RETURN_VELOCITY: i32 = 64;
+    // frames after getting back from a dive before it can dive again
+    pub const DIVE_COOLDOWN: i32 = 60;
+
+    pub const DEATH_FRAMES: i32 = 32;
+}
+
+enum BatState {
+    Flying(i32), // the frame it started flying again
+    Diving(i32), // start frame
+    Returning,   // flying back to where it left its swoop
+    Dying(i32),  // start frame
+}
+
+pub struct Bat<'a> {
+    enemy_info: EnemyInfo<'a>,
+    state: BatState,
+    home: Vector2D<FixedNumberType>,
+    swoop_frame: i32, // how far through the swoop it is, which only advances while flying
+}
+
+impl<'a> Bat<'a> {
+    fn new(object: &'a ObjectControl, start_pos: Vector2D<FixedNumberType>) -> Self {
+        let mut bat = Bat {
+            enemy_info: EnemyInfo::new(object, start_pos, (12u16, 8u16).into()),
+            state: BatState::Flying(0),
+            home: start_pos,
+            swoop_frame: 0,
+        };
+
+        bat.enemy_info.entity.sprite.set_sprite_size(Size::S16x16);
+        bat.enemy_info
+            .entity
+            .sprite
+            .set_tile_id(object_tiles::BAT_FLY_START);
+
+        bat
+    }
+
+    fn swoop_position(&self) -> Vector2D<FixedNumberType> {
+        let progress = FixedNumberType::new(self.swoop_frame) / bat_tuning::SWOOP_PERIOD;
+        self.home
+            + (
+                progress.sin() * bat_tuning::SWOOP_WIDTH,
+                (progress * 2).sin() * bat_tuning::SWOOP_HEIGHT,
+            )
+                .into()
+    }
+
+    fn face_velocity(&mut self) {
+        let velocity_x = self.enemy_info.entity.velocity.x;
+        if velocity_x != 0.into() {
+            self.enemy_info
+                .entity
+                .sprite
+                .set_hflip(velocity_x < 0.into());
+        }
+    }
+}
+
+impl<'a> EnemyBehaviour for Bat<'a> {
+    fn update(
+        &mut self,
+        level: &Level,
+        player_pos: Vector2D<FixedNumberType>,
+        hat_state: HatState,
+        timer: i32,
+        sfx_player: &mut SfxPlayer,
+    ) -> UpdateState {
+        let to_player = player_pos - self.enemy_info.entity.position;
+        let player_has_collided = to_player.magnitude_squared() < (10 * 10).into();
+
+        match self.state {
+            BatState::Flying(start_frame) => {
+                self.swoop_frame = (self.swoop_frame + 1) % bat_tuning::SWOOP_PERIOD;
+                let target = self.swoop_position();
+                self.enemy_info.entity.velocity = target - self.enemy_info.entity.position;
+                self.enemy_info.entity.position = target;
+
+                let flap = (timer / 6 % 2) as u16;
+                self.enemy_info
+                    .entity
+                    .sprite
+                    .set_tile_id(object_tiles::BAT_FLY_START + flap * 4);
+
+                if timer - start_frame > bat_tuning::DIVE_COOLDOWN
+                    && to_player.y > 0.into()
+                    && self
+                        .enemy_info
+                        .notices(level, player_pos, bat_tuning::DIVE_RANGE)
+                {
+                    self.state = BatState::Diving(timer);
+                    self.enemy_info.entity.velocity = to_player / to_player.magnitude()
+                        * sixty_fourths(bat_tuning::DIVE_VELOCITY);
+                    sfx_player.bat_dive();
+                }
+            }
+            BatState::Diving(start_frame) => {
+                self.enemy_info
+                    .entity
+                    .sprite
+                    .set_tile_id(object_tiles::BAT_DIVE);
+
+                // bats pull out of the dive when they would hit a wall
+                let next_position =
+                    self.enemy_info.entity.position + self.enemy_info.entity.velocity;
+                if timer - start_frame > bat_tuning::DIVE_FRAMES
+                    || self
+                        .enemy_info
+                        .entity
+                        .collision_at_point(level, next_position)
+                {
+                    self.state = BatState::Returning;
+                } else {
+                    self.enemy_info.entity.position = next_position;
+                }
+            }
+            BatState::Returning => {
+                // bats fly straight back over anything in the way
+                let to_swoop = self.swoop_position() - self.enemy_info.entity.position;
+                let distance = to_swoop.magnitude();
+                let speed = sixty_fourths(bat_tuning::RETURN_VELOCITY);
+
+                if distance <= speed {
+                    self.enemy_info.entity.position += to_swoop;
+                    self.state = BatState::Flying(timer);
+                } else {
+                    self.enemy_info.entity.velocity = to_swoop / distance * speed;
+                    self.enemy_info.entity.position += self.enemy_info.entity.velocity;
+                }
+
+                let flap = (timer / 4 % 2) as u16;
+                self.enemy_info
+                    .entity
+                    .sprite
+                    .set_tile_id(object_tiles::BAT_FLY_START + flap * 4);
+            }
+            BatState::Dying(start_frame) => {
+                if timer == start_frame + 1 {
+                    sfx_player.bat_death();
+                }
+
+                if timer - start_frame > bat_tuning::DEATH_FRAMES {
+                    return UpdateState::Remove;
+                }
+
+                // falls out of the sky, through anything underneath it
+                self.enemy_info.entity.velocity.x = 0.into();
+                self.enemy_info.entity.velocity.y += sixty_fourths(jump_tuning::GRAVITY);
+                self.enemy_info.entity.position += self.enemy_info.entity.velocity;
+                self.enemy_info
+                    .entity
+                    .sprite
+                    .set_tile_id(object_tiles::BAT_DEAD);
+
+                return UpdateState::Nothing;
+            }
+        }
+
+        self.face_velocity();
+
+        if player_has_collided {
+            if hat_state == HatState::WizardTowards {
+                self.state = BatState::Dying(timer);
                 self.enemy_info.entity.velocity = (0, 0).into();
+            } else {
+                return UpdateState::KillPlayer;
             }
         }
 
+        UpdateState::Nothing
+    }
+
+    fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.enemy_info.commit(background_offset);
+    }
+}
+
+// Turret tuning, distances in pixels and velocities in 64ths of a pixel per frame
+mod turret_tuning {
+    pub const FIRE_FRAMES: i32 = 90;
+    // the turret lights up for this long before firing, to warn the wizard
+    pub const CHARGE_FRAMES: i32 = 20;
+    pub const RANGE: i32 = 96;
+    pub const PROJECTILE_VELOCITY: i32 = 80;
+}
+
+// A turret which shoots at the wizard whenever it can see them close by
+pub struct Turret<'a> {
+    enemy_info: EnemyInfo<'a>,
+    last_fired: i32,
+}
+
+impl<'a> Turret<'a> {
+    fn new(object: &'a ObjectControl, start_pos: Vector2D<FixedNumberType>) -> Self {
+        let mut turret = Turret {
+            enemy_info: EnemyInfo::new(object, start_pos, (12u16, 12u16).into()),
+            last_fired: 0,
+        };
+
+        turret
+            .enemy_info
+            .entity
+            .sprite
+            .set_sprite_size(Size::S16x16);
+        turret
+            .enemy_info
+            .entity
+            .sprite
+            .set_tile_id(object_tiles::TURRET);
+
+        turret
+    }
+}
+
+impl<'a> EnemyBehaviour for Turret<'a> {
+    fn collides_with_hat(&self, position: Vector2D<FixedNumberType>) -> bool {
+        self.enemy_info.entity.is_within(position, 12)
+    }
+
+    fn update(
+        &mut self,
+        level: &Level,
+        player_pos: Vector2D<FixedNumberType>,
+        hat_state: HatState,
+        timer: i32,
+        sfx_player: &mut SfxPlayer,
+    ) -> UpdateState {
         self.enemy_info.update(level);
 
+        let barrel = self.enemy_info.entity.position - (0, 3).into();
+        let to_player = player_pos - barrel;
+
+        if to_player.magnitude_squared() < (10 * 10).into() {
+            if hat_state == HatState::WizardTowards {
+                sfx_player.turret_death();
+                return UpdateState::Remove;
+            } else {
+                return UpdateState::KillPlayer;
+            }
+        }
+
+        let in_range = self
+            .enemy_info
+            .notices(level, player_pos, turret_tuning::RANGE);
+        let frames_since_fired = timer - self.last_fired;
+
+        let tile = if in_range
+            && frames_since_fired >= turret_tuning::FIRE_FRAMES - turret_tuning::CHARGE_FRAMES
+        {
+            object_tiles::TURRET_CHARGED
+        } else {
+            object_tiles::TURRET
+        };
+        self.enemy_info.entity.sprite.set_tile_id(tile);
+
+        if in_range && frames_since_fired >= turret_tuning::FIRE_FRAMES {
+            self.last_fired = timer;
+            sfx_player.fire();
+
+            let velocity = to_player / to_player.magnitude()
+                * sixty_fourths(turret_tuning::PROJECTILE_VELOCITY);
+            return UpdateState::Fire(barrel, velocity);
+        }
+
         UpdateState::Nothing
     }
 
@@ -0,0 +1,25 @@
+// The kinds of enemy which can be placed in levels. This file is also included by build.rs, so
+// that levels are checked against the same list of object types when they are exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyType {
+    Slime,
+    Snail,
+    Bat,
+    Turret,
+    Patrol,
+}
+
+impl EnemyType {
+    // only build.rs looks enemies up by the type of the object which places them in Tiled
+    #[allow(dead_code)]
+    pub fn from_object_type(object_type: &str) -> Option<Self> {
+        match object_type {
+            "Slime Spawn" => Some(EnemyType::Slime),
+            "Snail Spawn" => Some(EnemyType::Snail),
+            "Bat Spawn" => Some(EnemyType::Bat),
+            "Turret Spawn" => Some(EnemyType::Turret),
+            "Patrol Spawn" => Some(EnemyType::Patrol),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use super::{object_tiles, sfx::SfxPlayer, Entity, FixedNumberType, HatState};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum KeyState {
+    Resting,
+    Carried, // follows the hat around, and so the wizard once the hat is back on their head
+}
+
+pub struct Key<'a> {
+    entity: Entity<'a>,
+    state: KeyState,
+}
+
+impl<'a> Key<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::KEY);
+
+        Key {
+            entity,
+            state: KeyState::Resting,
+        }
+    }
+
+    // puts the key back where it started
+    pub fn restart(&mut self, position: Vector2D<FixedNumberType>) {
+        self.entity.position = position;
+        self.state = KeyState::Resting;
+    }
+
+    // a key can only open doors once the hat has brought it back to the wizard
+    pub fn is_held(&self, hat_state: HatState) -> bool {
+        self.state == KeyState::Carried && hat_state == HatState::OnHead
+    }
+
+    pub fn update(
+        &mut self,
+        player_pos: Vector2D<FixedNumberType>,
+        hat_pos: Vector2D<FixedNumberType>,
+        hat_state: HatState,
+        sfx_player: &mut SfxPlayer,
+    ) {
+        match self.state {
+            KeyState::Resting => {
+                let touches =
+                    |position: Vector2D<FixedNumberType>| self.entity.is_within(position, 12);
+
+                let picked_up = if hat_state == HatState::OnHead {
+                    touches(player_pos)
+                } else {
+                    touches(hat_pos)
+                };
+
+                if picked_up {
+                    self.state = KeyState::Carried;
+                    sfx_player.collect();
+                }
+            }
+            KeyState::Carried => {
+                self.entity.position = hat_pos - (0, 10).into();
+            }
+        }
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
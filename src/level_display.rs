@@ -22,6 +22,8 @@ fn num_digits_iter(mut n: u32) -> impl core::iter::Iterator<Item = u8> {
 const LEVEL_START: u16 = 12 * 28;
 const NUMBERS_START: u16 = 12 * 28 + 3;
 const HYPHEN: u16 = 12 * 28 + 11;
+const COLLECTED: u16 = 14 * 28 + 1;
+const NOT_COLLECTED: u16 = 14 * 28 + 2;
 pub const BLANK: u16 = 11 * 28;
 
 pub fn new_map_store() -> [u16; 20] {
@@ -47,3 +49,25 @@ pub fn write_level(background: &mut BackgroundRegister, world: u32, level: u32)
 
     background.set_position((-(WIDTH / 2 - counter as i32 * 8 / 2), -(HEIGHT / 2 - 4)).into());
 }
+
+pub fn write_collectibles(background: &mut BackgroundRegister, collected: u32, total: u32) {
+    let map = background.get_block();
+
+    // only as many stars as fit across the screen are shown
+    let total = total.min(WIDTH as u32 / 8);
+    let collected = collected.min(total);
+
+    for tile in map[0].iter_mut() {
+        *tile = BLANK;
+    }
+
+    for i in 0..total as usize {
+        map[0][i] = if (i as u32) < collected {
+            COLLECTED
+        } else {
+            NOT_COLLECTED
+        };
+    }
+
+    background.set_position((-(WIDTH / 2 - total as i32 * 8 / 2), -(HEIGHT / 2 - 4)).into());
+}
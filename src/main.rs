@@ -1,21 +1,85 @@
 #![no_std]
 #![no_main]
 
+extern crate alloc;
+
+mod checkpoints;
+mod collectibles;
+mod debris;
 mod enemies;
+mod enemy_types;
+mod keys;
 mod level_display;
+mod projectiles;
 mod sfx;
 mod splash_screen;
+mod springs;
+mod switches;
+mod teleporters;
+mod water;
+mod wind;
+
+// x, y, width and height in pixels of a region of the level
+pub type Area = (i32, i32, i32, i32);
 
-pub struct Level {
+// the positions of the two ends of a pair of teleporters which are linked together
+pub type TeleporterPair = ((i32, i32), (i32, i32));
+
+#[derive(Clone)]
+pub struct Level<'a> {
     background: &'static [u16],
-    foreground: &'static [u16],
+    // The world layer. While playing, this is the buffer the layer is displayed from, which changes
+    // as doors are opened and blocks are broken or toggled.
+    foreground: &'a [u16],
     dimensions: Vector2D<u32>,
     collision: &'static [u32],
 
-    slimes: &'static [(i32, i32)],
-    snails: &'static [(i32, i32)],
+    enemies: &'static [enemies::EnemySpawn],
     enemy_stops: &'static [(i32, i32)],
+    checkpoints: &'static [(i32, i32)],
+    collectibles: &'static [(i32, i32)],
+    keys: &'static [(i32, i32)],
+    switches: &'static [(i32, i32)],
+    springs: &'static [(i32, i32)],
+    water: &'static [Area],
+    wind: &'static [(Area, (i32, i32))], // force in 64ths of a pixel per frame
+    teleporters: &'static [TeleporterPair],
     start_pos: (i32, i32),
+
+    rules: LevelRules,
+}
+
+// Gameplay rules which can be changed per level using properties on the map in Tiled
+#[derive(Clone)]
+pub struct LevelRules {
+    wall_resets_recalls: bool,
+    hat_bounces: i32, // number of times the thrown hat ricochets off walls, 0 to just stop
+    hat_platform: bool, // can the wizard stand on the hat while it hovers after being thrown
+    max_recalls: i8,
+    recall_refill: RecallRefill,
+    charged_throws: bool, // does holding A charge up the throw rather than throwing immediately
+    aim_preview: bool,    // show where the hat will go while aiming a throw
+    hat_teleports: bool,  // does the thrown hat go through teleporters as well as the wizard
+}
+
+// How the ground affects the wizard's movement, set using properties on tiles in Tiled
+#[derive(Clone, Copy)]
+pub struct Surface {
+    friction: i32,          // 64ths of the wizard's velocity kept each frame
+    conveyor_velocity: i32, // horizontal speed things on the surface are carried at, in 64ths
+}
+
+impl Surface {
+    const NORMAL: Surface = Surface {
+        friction: 54,
+        conveyor_velocity: 0,
+    };
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum RecallRefill {
+    Landing,     // recalls are refilled by landing on the ground
+    RefillTiles, // recalls are only refilled by touching a refill tile
 }
 
 mod object_tiles {
@@ -35,6 +99,39 @@ mod object_tiles {
     pub const SNAIL_EMERGE_START: u16 = 49 * 4;
     pub const SNAIL_MOVE: u16 = 54 * 4;
     pub const SNAIL_DEATH_START: u16 = 56 * 4;
+
+    pub const PATROL_START: u16 = 88 * 4;
+
+    pub const BAT_FLY_START: u16 = 90 * 4;
+    pub const BAT_DIVE: u16 = 92 * 4;
+    pub const BAT_DEAD: u16 = 93 * 4;
+
+    pub const TURRET: u16 = 94 * 4;
+    pub const TURRET_CHARGED: u16 = 95 * 4;
+    pub const PROJECTILE: u16 = 96 * 4;
+
+    pub const AIM_DOT: u16 = 65 * 4;
+    pub const AIM_DOT_SMALL: u16 = 65 * 4 + 1;
+
+    pub const CHECKPOINT_INACTIVE: u16 = 66 * 4;
+    pub const CHECKPOINT_RAISE_START: u16 = 67 * 4;
+    pub const CHECKPOINT_ACTIVE_START: u16 = 70 * 4;
+
+    pub const COLLECTIBLE_START: u16 = 72 * 4;
+
+    pub const KEY: u16 = 76 * 4;
+
+    pub const SWITCH_OFF: u16 = 77 * 4;
+    pub const SWITCH_ON: u16 = 78 * 4;
+
+    pub const DEBRIS_START: u16 = 79 * 4;
+
+    pub const SPRING: u16 = 83 * 4;
+    pub const SPRING_PRESSED: u16 = 84 * 4;
+
+    pub const WIND_PARTICLE: u16 = 85 * 4;
+
+    pub const TELEPORTER_START: u16 = 86 * 4;
 }
 
 agb::include_gfx!("gfx/object_sheet.toml");
@@ -42,7 +139,7 @@ agb::include_gfx!("gfx/object_sheet.toml");
 mod map_tiles {
 
     use super::Level;
-    pub const LEVELS: &[Level] = &[
+    pub const LEVELS: &[Level<'static>] = &[
         l1_1::get_level(),
         l1_2::get_level(),
         l1_3::get_level(),
@@ -99,13 +196,28 @@ mod map_tiles {
     pub mod tilemap {
         include!(concat!(env!("OUT_DIR"), "/tilemap.rs"));
     }
+
+    // the world layer of every level is copied into a buffer this size so it can be changed
+    pub const MAX_LEVEL_SIZE: usize = {
+        let mut max = 0;
+        let mut i = 0;
+        while i < LEVELS.len() {
+            if LEVELS[i].foreground.len() > max {
+                max = LEVELS[i].foreground.len();
+            }
+            i += 1;
+        }
+        max
+    };
 }
 
 agb::include_gfx!("gfx/tile_sheet.toml");
 
+use alloc::vec::Vec;
+
 use agb::{
     display::{
-        background::BackgroundRegular,
+        background::{BackgroundRegister, BackgroundRegular},
         object::{ObjectControl, ObjectStandard, Size},
         Priority, HEIGHT, WIDTH,
     },
@@ -134,11 +246,37 @@ impl<'a> Entity<'a> {
         }
     }
 
+    // a 16x16 sprite placed in the level, for objects which don't move around
+    pub fn new_at(
+        object: &'a ObjectControl,
+        position: Vector2D<FixedNumberType>,
+        tile: u16,
+    ) -> Self {
+        let mut entity = Entity::new(object, (16u16, 16u16).into());
+        entity.position = position;
+        entity.sprite.set_sprite_size(Size::S16x16);
+        entity.sprite.set_tile_id(tile);
+        entity
+    }
+
+    pub fn is_within(&self, position: Vector2D<FixedNumberType>, distance: i32) -> bool {
+        (self.position - position).magnitude_squared() < (distance * distance).into()
+    }
+
     fn something_at_point<T: Fn(i32, i32) -> bool>(
         &self,
         position: Vector2D<FixedNumberType>,
         something_fn: T,
     ) -> bool {
+        self.tile_at_point(position, something_fn).is_some()
+    }
+
+    // the first tile overlapping the entity at the given position for which something_fn is true
+    fn tile_at_point<T: Fn(i32, i32) -> bool>(
+        &self,
+        position: Vector2D<FixedNumberType>,
+        something_fn: T,
+    ) -> Option<(i32, i32)> {
         let left = (position.x - self.collision_mask.x as i32 / 2).floor() / 8;
         let right = (position.x + self.collision_mask.x as i32 / 2 - 1).floor() / 8;
         let top = (position.y - self.collision_mask.y as i32 / 2).floor() / 8;
@@ -147,17 +285,23 @@ impl<'a> Entity<'a> {
         for x in left..=right {
             for y in top..=bottom {
                 if something_fn(x, y) {
-                    return true;
+                    return Some((x, y));
                 }
             }
         }
-        false
+        None
     }
 
     fn collision_at_point(&self, level: &Level, position: Vector2D<FixedNumberType>) -> bool {
         self.something_at_point(position, |x, y| level.collides(x, y))
     }
 
+    // having just been launched upwards, such as by a bounce tile, doesn't count as being on the
+    // ground
+    fn is_on_ground(&self, level: &Level) -> bool {
+        self.velocity.y >= 0.into() && self.collision_at_point(level, self.position + (0, 1).into())
+    }
+
     fn killision_at_point(&self, level: &Level, position: Vector2D<FixedNumberType>) -> bool {
         self.something_at_point(position, |x, y| level.kills(x, y))
     }
@@ -166,6 +310,45 @@ impl<'a> Entity<'a> {
         self.something_at_point(position, |x, y| level.wins(x, y))
     }
 
+    fn refill_at_point(&self, level: &Level, position: Vector2D<FixedNumberType>) -> bool {
+        self.something_at_point(position, |x, y| level.refills(x, y))
+    }
+
+    fn door_at_point(
+        &self,
+        level: &Level,
+        position: Vector2D<FixedNumberType>,
+    ) -> Option<(i32, i32)> {
+        self.tile_at_point(position, |x, y| level.is_door(x, y))
+    }
+
+    // the velocity of the bounce tile at the given position, if there is one
+    fn bounce_at_point(
+        &self,
+        level: &Level,
+        position: Vector2D<FixedNumberType>,
+    ) -> Option<FixedNumberType> {
+        self.tile_at_point(position, |x, y| level.bounce_velocity(x, y).is_some())
+            .and_then(|(x, y)| level.bounce_velocity(x, y))
+    }
+
+    // the surface of the ground directly below the entity at the given position
+    fn surface_at_point(&self, level: &Level, position: Vector2D<FixedNumberType>) -> Surface {
+        self.tile_at_point(position + (0, 1).into(), |x, y| {
+            level.surface(x, y).is_some()
+        })
+        .and_then(|(x, y)| level.surface(x, y))
+        .unwrap_or(Surface::NORMAL)
+    }
+
+    fn breakable_at_point(
+        &self,
+        level: &Level,
+        position: Vector2D<FixedNumberType>,
+    ) -> Option<(i32, i32)> {
+        self.tile_at_point(position, |x, y| level.is_breakable(x, y))
+    }
+
     fn enemy_collision_at_point(
         &self,
         enemies: &[enemies::Enemy],
@@ -179,21 +362,30 @@ impl<'a> Entity<'a> {
         false
     }
 
-    // returns the distance actually moved
+    // returns the distance actually moved, or the velocity to launch upwards at after landing on
+    // a bounce tile
     fn update_position(&mut self, level: &Level) -> Vector2D<FixedNumberType> {
         let old_position = self.position;
-        let x_velocity = (self.velocity.x, 0.into()).into();
+        let velocity = self.water_velocity(level);
+
+        let x_velocity = (velocity.x, 0.into()).into();
         if !self.collision_at_point(level, self.position + x_velocity) {
             self.position += x_velocity;
         } else {
-            self.position += self.binary_search_collision(level, (1, 0).into(), self.velocity.x);
+            self.position += self.binary_search_collision(level, (1, 0).into(), velocity.x);
         }
 
-        let y_velocity = (0.into(), self.velocity.y).into();
+        let y_velocity = (0.into(), velocity.y).into();
         if !self.collision_at_point(level, self.position + y_velocity) {
             self.position += y_velocity;
         } else {
-            self.position += self.binary_search_collision(level, (0, 1).into(), self.velocity.y);
+            self.position += self.binary_search_collision(level, (0, 1).into(), velocity.y);
+
+            if self.velocity.y > 0.into() {
+                if let Some(bounce) = self.bounce_at_point(level, self.position + y_velocity) {
+                    return (self.position.x - old_position.x, -bounce).into();
+                }
+            }
         }
 
         self.position - old_position
@@ -206,7 +398,9 @@ impl<'a> Entity<'a> {
     ) -> (Vector2D<FixedNumberType>, bool) {
         let mut was_enemy_collision = false;
         let old_position = self.position;
-        let x_velocity = (self.velocity.x, 0.into()).into();
+        let velocity = self.water_velocity(level);
+
+        let x_velocity = (velocity.x, 0.into()).into();
 
         if !(self.collision_at_point(level, self.position + x_velocity)
             || self.enemy_collision_at_point(enemies, self.position + x_velocity))
@@ -217,7 +411,7 @@ impl<'a> Entity<'a> {
             was_enemy_collision = true;
         }
 
-        let y_velocity = (0.into(), self.velocity.y).into();
+        let y_velocity = (0.into(), velocity.y).into();
         if !(self.collision_at_point(level, self.position + y_velocity)
             || self.enemy_collision_at_point(enemies, self.position + y_velocity))
         {
@@ -230,6 +424,16 @@ impl<'a> Entity<'a> {
         (self.position - old_position, was_enemy_collision)
     }
 
+    // things only move part of the way they otherwise would through water, which slows them down
+    // since the distance moved becomes their new velocity
+    fn water_velocity(&self, level: &Level) -> Vector2D<FixedNumberType> {
+        if level.in_water(self.position) {
+            self.velocity * water_tuning::DRAG / 64
+        } else {
+            self.velocity
+        }
+    }
+
     fn binary_search_collision(
         &self,
         level: &Level,
@@ -270,7 +474,9 @@ struct Map<'a, 'b> {
     background: &'a mut BackgroundRegular<'b>,
     foreground: &'a mut BackgroundRegular<'b>,
     position: Vector2D<FixedNumberType>,
-    level: &'a Level,
+    level: Level<'static>,
+    blocks_toggled: bool, // have the toggle blocks been flipped by a switch
+    broken_blocks: Vec<(i32, i32)>, // blocks broken this frame, which still need debris spawning
 }
 
 impl<'a, 'b, 'c> Map<'a, 'b> {
@@ -282,16 +488,83 @@ impl<'a, 'b, 'c> Map<'a, 'b> {
         self.foreground.commit();
     }
 
+    // the world layer is displayed from a mutable buffer so that tiles can change during the level
     fn load_foreground(&'c mut self) -> impl Iterator<Item = ()> + 'c {
         self.background.set_position(self.position.floor());
-        self.background.set_map(agb::display::background::Map::new(
-            self.level.foreground,
-            self.level.dimensions,
-            0,
-        ));
+        self.reset_foreground();
         self.background.commit_partial()
     }
 
+    // puts the world layer back to how it was at the start of the level
+    fn reset_foreground(&mut self) {
+        self.blocks_toggled = false;
+
+        let (dimensions, foreground) = (self.level.dimensions, self.level.foreground);
+        let map = self.world_tiles();
+        map.dimensions = dimensions;
+        map.get_mutable_store()[..foreground.len()].copy_from_slice(foreground);
+    }
+
+    fn world_tiles(&mut self) -> &mut agb::display::background::Map<'b> {
+        self.background
+            .get_map()
+            .expect("The world layer needs a buffer to be displayed from")
+    }
+
+    // The level as it is now. The world layer is read straight from the buffer it is displayed
+    // from, so there is only one copy of it to keep up to date.
+    fn current_level(&mut self) -> Level {
+        let level = self.level.clone();
+
+        Level {
+            foreground: self.world_tiles().get_store(),
+            ..level
+        }
+    }
+
+    fn set_tile(&mut self, x: i32, y: i32, tile: u16) {
+        let position = (self.level.dimensions.x as i32 * y + x) as usize;
+        self.world_tiles().get_mutable_store()[position] = tile;
+    }
+
+    fn toggle_blocks(&mut self) {
+        self.blocks_toggled = !self.blocks_toggled;
+
+        let size = self.level.foreground.len();
+        for tile in self.world_tiles().get_mutable_store()[..size].iter_mut() {
+            for &(solid, passable) in map_tiles::tilemap::TOGGLE_TILES {
+                if *tile == solid {
+                    *tile = passable;
+                } else if *tile == passable {
+                    *tile = solid;
+                }
+            }
+        }
+    }
+
+    // opens the door at the given tile along with every door tile connected to it
+    fn open_door(&mut self, x: i32, y: i32) {
+        let mut to_open = Vec::new();
+        to_open.push((x, y));
+
+        while let Some((x, y)) = to_open.pop() {
+            if self.current_level().is_door(x, y) {
+                self.set_tile(x, y, map_tiles::tilemap::BLANK_TILE);
+                to_open.extend_from_slice(&[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+            }
+        }
+    }
+
+    // breaks any breakable blocks the entity is about to move into
+    fn break_blocks_ahead(&mut self, entity: &Entity) {
+        let next_position = entity.position + entity.velocity;
+
+        while let Some((x, y)) = entity.breakable_at_point(&self.current_level(), next_position) {
+            self.set_tile(x, y, map_tiles::tilemap::BLANK_TILE);
+            self.broken_blocks.push((x, y));
+        }
+    }
+
     fn load_background(&'c mut self) -> impl Iterator<Item = ()> + 'c {
         self.foreground.set_position(self.position.floor());
         self.foreground.set_map(agb::display::background::Map::new(
@@ -304,15 +577,77 @@ impl<'a, 'b, 'c> Map<'a, 'b> {
     }
 }
 
-impl Level {
+impl<'a> Level<'a> {
+    // Walks through the tiles on the line between the two points, in the order the line crosses
+    // them, and checks none of them are solid.
+    fn has_line_of_sight(
+        &self,
+        from: Vector2D<FixedNumberType>,
+        to: Vector2D<FixedNumberType>,
+    ) -> bool {
+        let (from, to) = (from.floor(), to.floor());
+        let mut tile = from / 8;
+        let end = to / 8;
+
+        let distance = to - from;
+        let (distance_x, distance_y) = (distance.x.abs(), distance.y.abs());
+        let step: Vector2D<i32> = (distance.x.signum(), distance.y.signum()).into();
+
+        // how far the line has to go along each axis to reach the next column or row of tiles
+        let mut next_x = if step.x > 0 {
+            (tile.x + 1) * 8 - from.x
+        } else {
+            from.x - tile.x * 8
+        };
+        let mut next_y = if step.y > 0 {
+            (tile.y + 1) * 8 - from.y
+        } else {
+            from.y - tile.y * 8
+        };
+
+        while tile != end {
+            // the line reaches the next column first if next_x / distance_x < next_y / distance_y
+            let step_x = if tile.x == end.x {
+                false
+            } else if tile.y == end.y {
+                true
+            } else {
+                next_x * distance_y < next_y * distance_x
+            };
+
+            if step_x {
+                tile.x += step.x;
+                next_x += 8;
+            } else {
+                tile.y += step.y;
+                next_y += 8;
+            }
+
+            if self.collides(tile.x, tile.y) {
+                return false;
+            }
+        }
+
+        true
+    }
+
     fn collides(&self, x: i32, y: i32) -> bool {
-        self.at_point(x, y, map_tiles::tilemap::COLLISION_TILE as u32)
+        // toggle blocks are swapped over in the level's tiles when they are flipped, so whichever
+        // are solid at the moment always use the solid tile
+        const SOLID_TILES: u32 = map_tiles::tilemap::COLLISION_TILE as u32
+            | map_tiles::tilemap::DOOR_TILE as u32
+            | map_tiles::tilemap::BREAKABLE_TILE as u32
+            | map_tiles::tilemap::BOUNCE_TILE as u32
+            | map_tiles::tilemap::TOGGLE_SOLID_TILE as u32;
+
+        self.at_point(x, y, SOLID_TILES)
     }
 
     fn kills(&self, x: i32, y: i32) -> bool {
         self.at_point(x, y, map_tiles::tilemap::KILL_TILE as u32)
     }
 
+    // tile types are each a single bit, so several types can be checked for at once
     fn at_point(&self, x: i32, y: i32, tile: u32) -> bool {
         if (x < 0 || x >= self.dimensions.x as i32) || (y < 0 || y >= self.dimensions.y as i32) {
             return true;
@@ -322,12 +657,84 @@ impl Level {
         let tile_background = self.background[pos];
         let foreground_tile_property = self.collision[tile_foreground as usize];
         let background_tile_property = self.collision[tile_background as usize];
-        foreground_tile_property == tile || background_tile_property == tile
+        (foreground_tile_property | background_tile_property) & tile != 0
     }
 
     fn wins(&self, x: i32, y: i32) -> bool {
         self.at_point(x, y, map_tiles::tilemap::WIN_TILE as u32)
     }
+
+    fn refills(&self, x: i32, y: i32) -> bool {
+        self.at_point(x, y, map_tiles::tilemap::REFILL_TILE as u32)
+    }
+
+    fn bounce_velocity(&self, x: i32, y: i32) -> Option<FixedNumberType> {
+        if (x < 0 || x >= self.dimensions.x as i32) || (y < 0 || y >= self.dimensions.y as i32) {
+            return None;
+        }
+        let pos = (self.dimensions.x as i32 * y + x) as usize;
+        let tiles = [self.foreground[pos], self.background[pos]];
+
+        map_tiles::tilemap::BOUNCE_VELOCITIES
+            .iter()
+            .find(|(tile, _)| tiles.contains(tile))
+            .map(|&(_, velocity)| sixty_fourths(velocity))
+    }
+
+    fn surface(&self, x: i32, y: i32) -> Option<Surface> {
+        if (x < 0 || x >= self.dimensions.x as i32) || (y < 0 || y >= self.dimensions.y as i32) {
+            return None;
+        }
+        let pos = (self.dimensions.x as i32 * y + x) as usize;
+        let tiles = [self.foreground[pos], self.background[pos]];
+
+        map_tiles::tilemap::SURFACES
+            .iter()
+            .find(|(tile, _)| tiles.contains(tile))
+            .map(|&(_, surface)| surface)
+    }
+
+    fn in_water(&self, position: Vector2D<FixedNumberType>) -> bool {
+        let position = position.floor();
+        self.water.iter().any(|&area| in_area(area, position))
+    }
+
+    fn in_wind(&self, position: Vector2D<FixedNumberType>) -> bool {
+        let position = position.floor();
+        self.wind.iter().any(|&(area, _)| in_area(area, position))
+    }
+
+    // the total force of all the wind blowing at this position
+    fn wind_at(&self, position: Vector2D<FixedNumberType>) -> Vector2D<FixedNumberType> {
+        let position = position.floor();
+        self.wind
+            .iter()
+            .filter(|&&(area, _)| in_area(area, position))
+            .fold((0, 0).into(), |total, &(_, (x, y))| {
+                total + (sixty_fourths(x), sixty_fourths(y)).into()
+            })
+    }
+
+    fn water_at_tile(&self, x: i32, y: i32) -> bool {
+        self.in_water((x * 8 + 4, y * 8 + 4).into())
+    }
+
+    // doors are only looked for in the world layer, since that is the one which can be changed
+    fn is_door(&self, x: i32, y: i32) -> bool {
+        self.foreground_at_point(x, y, map_tiles::tilemap::DOOR_TILE as u32)
+    }
+
+    fn is_breakable(&self, x: i32, y: i32) -> bool {
+        self.foreground_at_point(x, y, map_tiles::tilemap::BREAKABLE_TILE as u32)
+    }
+
+    fn foreground_at_point(&self, x: i32, y: i32, tile: u32) -> bool {
+        if (x < 0 || x >= self.dimensions.x as i32) || (y < 0 || y >= self.dimensions.y as i32) {
+            return false;
+        }
+        let pos = (self.dimensions.x as i32 * y + x) as usize;
+        self.collision[self.foreground[pos] as usize] == tile
+    }
 }
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -337,15 +744,134 @@ pub enum HatState {
     WizardTowards,
 }
 
+enum HatCollision {
+    None,
+    Enemy,
+    Wall,
+    Bounce,
+}
+
+const AIM_PREVIEW_DOTS: usize = 8;
+const AIM_PREVIEW_FRAMES_PER_DOT: i32 = 3;
+
+struct AimPreview<'a> {
+    dots: [ObjectStandard<'a>; AIM_PREVIEW_DOTS],
+    path: [Option<Vector2D<FixedNumberType>>; AIM_PREVIEW_DOTS],
+}
+
+impl<'a> AimPreview<'a> {
+    fn new(controller: &'a ObjectControl) -> Self {
+        let dots = [(); AIM_PREVIEW_DOTS].map(|_| {
+            let mut dot = controller.get_object_standard();
+            dot.set_sprite_size(Size::S8x8);
+            dot.set_priority(Priority::P1);
+            dot
+        });
+
+        AimPreview {
+            dots,
+            path: [None; AIM_PREVIEW_DOTS],
+        }
+    }
+
+    fn commit(&mut self, offset: Vector2D<FixedNumberType>) {
+        for (i, (dot, position)) in self.dots.iter_mut().zip(self.path.iter()).enumerate() {
+            let position = position.map(|position| (position - offset).floor());
+            match position {
+                Some(position)
+                    if position.x >= -4
+                        && position.x <= WIDTH + 4
+                        && position.y >= -4
+                        && position.y <= HEIGHT + 4 =>
+                {
+                    // dots further along the path are smaller
+                    dot.set_tile_id(if i < AIM_PREVIEW_DOTS / 2 {
+                        object_tiles::AIM_DOT
+                    } else {
+                        object_tiles::AIM_DOT_SMALL
+                    });
+                    dot.set_position(position - (4, 4).into());
+                    dot.show();
+                }
+                _ => dot.hide(),
+            }
+            dot.commit();
+        }
+    }
+}
+
+// Jump tuning, all in 64ths of a pixel per frame
+mod jump_tuning {
+    pub const GRAVITY: i32 = 4;
+    // upward velocity given to the wizard on the first frame of the jump
+    pub const JUMP_VELOCITY: i32 = 80;
+    // while B is held for the first HOLD_FRAMES frames, gravity is reduced to HOLD_GRAVITY
+    pub const HOLD_FRAMES: i32 = 8;
+    pub const HOLD_GRAVITY: i32 = 1;
+    // releasing B while rising caps the upward velocity to this
+    pub const RELEASE_VELOCITY: i32 = 24;
+
+    // maximum falling speed while pushing into a wall
+    pub const WALL_SLIDE_VELOCITY: i32 = 16;
+    // horizontal velocity away from the wall given by a wall jump
+    pub const WALL_JUMP_VELOCITY: i32 = 64;
+
+    // upward velocity given by landing on a spring
+    pub const SPRING_VELOCITY: i32 = 192;
+}
+
+// in 64ths of a pixel per frame
+mod water_tuning {
+    // 64ths of their velocity things actually move by each frame while in water
+    pub const DRAG: i32 = 56;
+    pub const GRAVITY: i32 = 1;
+    pub const MAX_FALL_VELOCITY: i32 = 32;
+    // upward velocity given by each press of B while swimming
+    pub const STROKE_VELOCITY: i32 = 112;
+}
+
+mod wind_tuning {
+    // the wizard is much heavier than their hat, so is only pushed by a fraction of the wind
+    pub const WIZARD_DIVIDER: i32 = 4;
+    // how many times faster than the wind's force the wind particles move
+    pub const PARTICLE_SPEED: i32 = 16;
+    pub const PARTICLE_SPAWN_FRAMES: i32 = 6;
+}
+
+// Throw tuning, all in 64ths of a pixel per frame
+mod throw_tuning {
+    pub const THROW_VELOCITY: i32 = 320;
+    // with charged throws, the speed goes from MIN to MAX over CHARGE_FRAMES frames of holding A
+    pub const MIN_CHARGED_VELOCITY: i32 = 128;
+    pub const MAX_CHARGED_VELOCITY: i32 = 384;
+    pub const CHARGE_FRAMES: i32 = 30;
+    // the thrown hat smashes breakable blocks if it is going at least this fast
+    pub const BREAK_VELOCITY: i32 = 192;
+}
+
+fn in_area((x, y, width, height): Area, position: Vector2D<i32>) -> bool {
+    position.x >= x && position.x < x + width && position.y >= y && position.y < y + height
+}
+
+fn sixty_fourths(n: i32) -> FixedNumberType {
+    FixedNumberType::new(n) / 64
+}
+
 struct Player<'a> {
     wizard: Entity<'a>,
     hat: Entity<'a>,
     hat_state: HatState,
     hat_left_range: bool,
     hat_slow_counter: i32,
+    hat_bounces: i32,
+    throw_charge: Option<i32>,
+    charge_spin: i32, // how far the hat has spun while charging, in 16ths of an animation frame
+    aim_preview: AimPreview<'a>,
     wizard_frame: u8,
     num_recalls: i8,
     is_on_ground: bool,
+    is_jumping: bool,
+    jump_frames: i32,
     facing: input::Tri,
 }
 
@@ -374,56 +900,314 @@ impl<'a> Player<'a> {
         wizard.sprite.commit();
         hat.sprite.commit();
 
-        wizard.position = start_position;
-        hat.position = start_position - (0, 10).into();
-
-        Player {
+        let mut player = Player {
             wizard,
             hat,
             hat_slow_counter: 0,
+            hat_bounces: 0,
+            throw_charge: None,
+            charge_spin: 0,
+            aim_preview: AimPreview::new(controller),
             hat_state: HatState::OnHead,
             hat_left_range: false,
             wizard_frame: 0,
             num_recalls: 0,
             is_on_ground: true,
+            is_jumping: false,
+            jump_frames: 0,
             facing: input::Tri::Zero,
+        };
+        player.restart(start_position);
+
+        player
+    }
+
+    // puts the player back as they were at the start of the level, reusing the same sprites
+    fn restart(&mut self, start_position: Vector2D<FixedNumberType>) {
+        self.wizard
+            .sprite
+            .set_tile_id(object_tiles::WIZARD_TILE_START);
+        self.wizard.sprite.set_priority(Priority::P1); // the wizard is drawn on top while dying
+        self.wizard.sprite.set_hflip(false);
+        self.hat.sprite.set_tile_id(object_tiles::HAT_TILE_START);
+
+        self.wizard.position = start_position;
+        self.wizard.velocity = (0, 0).into();
+        self.hat.position = start_position - (0, 10).into();
+        self.hat.velocity = (0, 0).into();
+
+        self.hat_slow_counter = 0;
+        self.hat_bounces = 0;
+        self.throw_charge = None;
+        self.charge_spin = 0;
+        self.aim_preview.path = [None; AIM_PREVIEW_DOTS];
+        self.hat_state = HatState::OnHead;
+        self.hat_left_range = false;
+        self.wizard_frame = 0;
+        self.num_recalls = 0;
+        self.is_on_ground = true;
+        self.is_jumping = false;
+        self.jump_frames = 0;
+        self.facing = input::Tri::Zero;
+    }
+
+    // while the thrown hat is hovering in place it can be stood on, if the level allows it
+    fn hat_is_platform(&self, level: &Level) -> bool {
+        level.rules.hat_platform
+            && self.hat_state == HatState::Thrown
+            && self.hat_slow_counter > 0
+            && self.hat.velocity == (0, 0).into()
+    }
+
+    // the distance from the bottom of the wizard to the top of the hat, if the wizard is lined up
+    // horizontally with the hat
+    fn height_above_hat(&self) -> Option<FixedNumberType> {
+        let half_widths = (self.wizard.collision_mask.x + self.hat.collision_mask.x) as i32 / 2;
+        if (self.wizard.position.x - self.hat.position.x).abs() >= half_widths.into() {
+            return None;
         }
+
+        let wizard_bottom = self.wizard.position.y + self.wizard.collision_mask.y as i32 / 2;
+        let hat_top = self.hat.position.y - self.hat.collision_mask.y as i32 / 2;
+        Some(hat_top - wizard_bottom)
+    }
+
+    fn throw_speed(&self, level: &Level) -> FixedNumberType {
+        if level.rules.charged_throws {
+            let charge = self.throw_charge.unwrap_or(0);
+            sixty_fourths(
+                throw_tuning::MIN_CHARGED_VELOCITY
+                    + (throw_tuning::MAX_CHARGED_VELOCITY - throw_tuning::MIN_CHARGED_VELOCITY)
+                        * charge
+                        / throw_tuning::CHARGE_FRAMES,
+            )
+        } else {
+            sixty_fourths(throw_tuning::THROW_VELOCITY)
+        }
+    }
+
+    // the velocity the hat would be thrown with, or None if there is no direction to throw it in
+    fn throw_velocity(
+        &self,
+        input: &ButtonController,
+        speed: FixedNumberType,
+    ) -> Option<Vector2D<FixedNumberType>> {
+        let direction: Vector2D<FixedNumberType> = {
+            let up_down = input.y_tri() as i32;
+            let left_right = if up_down == 0 {
+                self.facing as i32
+            } else {
+                input.x_tri() as i32
+            };
+            (left_right, up_down).into()
+        };
+
+        if direction == (0, 0).into() {
+            return None;
+        }
+
+        let mut velocity = direction.normalise() * speed;
+        if velocity.y > 0.into() {
+            velocity.y *= FixedNumberType::new(4) / 3;
+        }
+        Some(velocity)
+    }
+
+    fn throw_hat(
+        &mut self,
+        input: &ButtonController,
+        level: &Level,
+        sfx_player: &mut sfx::SfxPlayer,
+    ) {
+        if let Some(velocity) = self.throw_velocity(input, self.throw_speed(level)) {
+            self.hat.velocity = velocity;
+            self.hat_state = HatState::Thrown;
+            self.hat_bounces = level.rules.hat_bounces;
+
+            sfx_player.throw();
+        }
+    }
+
+    // sets the velocity the thrown hat moves with this frame, slowing down and then homing in on
+    // target
+    fn accelerate_thrown_hat(
+        &mut self,
+        level: &Level,
+        target: Vector2D<FixedNumberType>,
+        hold_still: bool,
+    ) {
+        let distance_vector = target - self.hat.position;
+        let distance = distance_vector.magnitude();
+        let direction = if distance == 0.into() {
+            (0, 0).into()
+        } else {
+            distance_vector / distance
+        };
+
+        // the wind keeps pushing the hat along even while it hovers
+        let wind = level.wind_at(self.hat.position);
+        if self.hat_slow_counter < 30 && self.hat.velocity.magnitude() < 2.into() {
+            self.hat.velocity = wind;
+            if !hold_still {
+                self.hat_slow_counter += 1;
+            }
+        } else {
+            self.hat.velocity += direction / 4 + wind;
+        }
+    }
+
+    // moves the thrown hat by one frame at the velocity set by accelerate_thrown_hat
+    fn move_thrown_hat(&mut self, level: &Level, enemies: &[enemies::Enemy]) -> HatCollision {
+        let velocity = self.hat.velocity;
+        let (new_velocity, enemy_collision) = self.hat.update_position_with_enemy(level, enemies);
+        self.hat.velocity = new_velocity;
+
+        if enemy_collision {
+            return HatCollision::Enemy;
+        }
+
+        // the hat doesn't move at all along an axis where it hit a wall
+        let hit_wall_x = velocity.x != 0.into() && new_velocity.x == 0.into();
+        let hit_wall_y = velocity.y != 0.into() && new_velocity.y == 0.into();
+
+        // bounce tiles always send the hat back, whether or not it ricochets off other walls
+        let hit_bounce_x = hit_wall_x
+            && self
+                .hat
+                .bounce_at_point(level, self.hat.position + (velocity.x, 0.into()).into())
+                .is_some();
+        let hit_bounce_y = hit_wall_y
+            && self
+                .hat
+                .bounce_at_point(level, self.hat.position + (0.into(), velocity.y).into())
+                .is_some();
+
+        if hit_bounce_x || hit_bounce_y {
+            if hit_bounce_x {
+                self.hat.velocity.x = -velocity.x;
+            }
+            if hit_bounce_y {
+                self.hat.velocity.y = -velocity.y;
+            }
+            return HatCollision::Bounce;
+        }
+
+        if self.hat_bounces > 0 {
+            if hit_wall_x {
+                self.hat.velocity.x = -velocity.x;
+            }
+            if hit_wall_y {
+                self.hat.velocity.y = -velocity.y;
+            }
+
+            if hit_wall_x || hit_wall_y {
+                self.hat_bounces -= 1;
+                return HatCollision::Wall;
+            }
+        }
+
+        HatCollision::None
+    }
+
+    // Springs, teleporters and breakable blocks change the hat's path outside of its own physics,
+    // so the aim preview stops where the hat would reach one rather than showing the wrong path.
+    fn hat_reaches_level_object(&self, level: &Level) -> bool {
+        let position = self.hat.position;
+        let near = |point: (i32, i32), distance: i32| {
+            (Vector2D::<FixedNumberType>::from(point) - position).magnitude_squared()
+                < (distance * distance).into()
+        };
+
+        let reaches_spring = level.springs.iter().any(|&spring| near(spring, 12));
+        let reaches_teleporter = level.rules.hat_teleports
+            && level
+                .teleporters
+                .iter()
+                .any(|&(from, to)| near(from, 8) || near(to, 8));
+        let reaches_breakable = self.hat.velocity.magnitude()
+            >= sixty_fourths(throw_tuning::BREAK_VELOCITY)
+            && self
+                .hat
+                .breakable_at_point(level, position + self.hat.velocity)
+                .is_some();
+
+        reaches_spring || reaches_teleporter || reaches_breakable
+    }
+
+    // Runs the thrown hat's physics from the wizard's head to find where a throw would go, using
+    // the hat itself and then putting it back where it was.
+    fn predict_throw(
+        &mut self,
+        input: &ButtonController,
+        level: &Level,
+        enemies: &[enemies::Enemy],
+        hat_resting_position: Vector2D<FixedNumberType>,
+    ) -> [Option<Vector2D<FixedNumberType>>; AIM_PREVIEW_DOTS] {
+        let mut path = [None; AIM_PREVIEW_DOTS];
+
+        let velocity = match self.throw_velocity(input, self.throw_speed(level)) {
+            Some(velocity) => velocity,
+            None => return path,
+        };
+
+        let saved_position = self.hat.position;
+        let saved_bounces = self.hat_bounces;
+
+        self.hat.velocity = velocity;
+        self.hat_bounces = level.rules.hat_bounces;
+        let target = self.wizard.position - hat_resting_position;
+        let mut left_range = false;
+
+        'simulation: for dot in path.iter_mut() {
+            for _ in 0..AIM_PREVIEW_FRAMES_PER_DOT {
+                let distance = (target - self.hat.position).magnitude();
+                if distance > 16.into() {
+                    left_range = true;
+                }
+                if left_range && distance < 16.into() {
+                    break 'simulation;
+                }
+                if self.hat_reaches_level_object(level) {
+                    *dot = Some(self.hat.position);
+                    break 'simulation;
+                }
+
+                self.accelerate_thrown_hat(level, target, false);
+                self.move_thrown_hat(level, enemies);
+            }
+            *dot = Some(self.hat.position);
+        }
+
+        self.hat.position = saved_position;
+        self.hat.velocity = (0, 0).into();
+        self.hat_slow_counter = 0;
+        self.hat_bounces = saved_bounces;
+
+        path
     }
 
     fn update_frame(
         &mut self,
         input: &ButtonController,
         timer: i32,
-        level: &Level,
+        map: &mut Map,
         enemies: &[enemies::Enemy],
         sfx_player: &mut sfx::SfxPlayer,
     ) {
+        let mut level = map.current_level();
+
         // throw or recall
         if input.is_just_pressed(Button::A) {
             if self.hat_state == HatState::OnHead {
-                let direction: Vector2D<FixedNumberType> = {
-                    let up_down = input.y_tri() as i32;
-                    let left_right = if up_down == 0 {
-                        self.facing as i32
-                    } else {
-                        input.x_tri() as i32
-                    };
-                    (left_right, up_down).into()
-                };
-
-                if direction != (0, 0).into() {
-                    let mut velocity = direction.normalise() * 5;
-                    if velocity.y > 0.into() {
-                        velocity.y *= FixedNumberType::new(4) / 3;
-                    }
-                    self.hat.velocity = velocity;
-                    self.hat_state = HatState::Thrown;
-
-                    sfx_player.throw();
+                if level.rules.charged_throws {
+                    self.throw_charge = Some(0);
+                    self.charge_spin = 0;
+                } else {
+                    self.throw_hat(input, &level, sfx_player);
                 }
             } else if self.hat_state == HatState::Thrown {
-                self.num_recalls += 1;
-                if self.num_recalls < 3 {
+                if self.num_recalls < level.rules.max_recalls {
+                    self.num_recalls += 1;
                     self.hat.velocity = (0, 0).into();
                     self.wizard.velocity = (0, 0).into();
                     self.hat_state = HatState::WizardTowards;
@@ -434,37 +1218,166 @@ impl<'a> Player<'a> {
             }
         }
 
+        if let Some(charge) = self.throw_charge {
+            if input.is_pressed(Button::A) {
+                self.throw_charge = Some((charge + 1).min(throw_tuning::CHARGE_FRAMES));
+            } else {
+                self.throw_hat(input, &level, sfx_player);
+                self.throw_charge = None;
+            }
+        }
+
         let was_on_ground = self.is_on_ground;
-        let is_on_ground = self
-            .wizard
-            .collision_at_point(level, self.wizard.position + (0, 1).into());
+        let is_on_hat = self.hat_is_platform(&level)
+            && self
+                .height_above_hat()
+                .map_or(false, |height| height >= 0.into() && height <= 1.into());
+        let is_on_level_ground = self.wizard.is_on_ground(&level);
+        let is_on_ground = is_on_level_ground || is_on_hat;
 
         if is_on_ground && !was_on_ground && self.wizard.velocity.y > 1.into() {
             sfx_player.land();
         }
         self.is_on_ground = is_on_ground;
 
+        let wall_direction = if self
+            .wizard
+            .collision_at_point(&level, self.wizard.position + (1, 0).into())
+        {
+            input::Tri::Positive
+        } else if self
+            .wizard
+            .collision_at_point(&level, self.wizard.position - (1, 0).into())
+        {
+            input::Tri::Negative
+        } else {
+            input::Tri::Zero
+        };
+
+        let is_wall_sliding =
+            !is_on_ground && wall_direction != input::Tri::Zero && input.x_tri() == wall_direction;
+
         if self.hat_state != HatState::WizardTowards {
-            if is_on_ground {
+            let refill_recalls = match level.rules.recall_refill {
+                RecallRefill::Landing => {
+                    is_on_level_ground || (is_wall_sliding && level.rules.wall_resets_recalls)
+                }
+                RecallRefill::RefillTiles => {
+                    self.wizard.refill_at_point(&level, self.wizard.position)
+                }
+            };
+            if refill_recalls {
                 self.num_recalls = 0;
             }
 
             if is_on_ground {
+                let surface = if is_on_level_ground {
+                    self.wizard.surface_at_point(&level, self.wizard.position)
+                } else {
+                    Surface::NORMAL
+                };
+
                 self.wizard.velocity.x += FixedNumberType::new(input.x_tri() as i32) / 16;
-                self.wizard.velocity = self.wizard.velocity * 54 / 64;
+                self.wizard.velocity = self.wizard.velocity * surface.friction / 64;
+                // pushes the wizard towards the speed of the conveyor, and no faster
+                self.wizard.velocity.x +=
+                    sixty_fourths(surface.conveyor_velocity * (64 - surface.friction)) / 64;
                 if input.is_just_pressed(Button::B) {
-                    self.wizard.velocity.y = -FixedNumberType::new(3) / 2;
+                    self.wizard.velocity.y = -sixty_fourths(jump_tuning::JUMP_VELOCITY);
+                    self.is_jumping = true;
+                    self.jump_frames = 0;
                     sfx_player.jump();
                 }
+            } else if level.in_water(self.wizard.position) {
+                self.wizard.velocity.x += FixedNumberType::new(input.x_tri() as i32) / 32;
+                self.wizard.velocity.y += sixty_fourths(water_tuning::GRAVITY);
+                self.is_jumping = false;
+
+                if input.is_just_pressed(Button::B) {
+                    self.wizard.velocity.y = -sixty_fourths(water_tuning::STROKE_VELOCITY);
+                    sfx_player.swim();
+                }
+
+                self.wizard.velocity.y = self
+                    .wizard
+                    .velocity
+                    .y
+                    .min(sixty_fourths(water_tuning::MAX_FALL_VELOCITY));
             } else {
                 self.wizard.velocity.x += FixedNumberType::new(input.x_tri() as i32) / 64;
                 self.wizard.velocity = self.wizard.velocity * 63 / 64;
-                let gravity: Vector2D<FixedNumberType> = (0, 1).into();
-                let gravity = gravity / 16;
-                self.wizard.velocity += gravity;
+
+                let mut gravity = jump_tuning::GRAVITY;
+                if self.is_jumping {
+                    if input.is_pressed(Button::B) {
+                        if self.jump_frames < jump_tuning::HOLD_FRAMES {
+                            gravity = jump_tuning::HOLD_GRAVITY;
+                        }
+                    } else {
+                        // let go of B early, so cut the jump short
+                        self.wizard.velocity.y = self
+                            .wizard
+                            .velocity
+                            .y
+                            .max(-sixty_fourths(jump_tuning::RELEASE_VELOCITY));
+                        self.is_jumping = false;
+                    }
+                    self.jump_frames += 1;
+                }
+
+                self.wizard.velocity.y += sixty_fourths(gravity);
+
+                if wall_direction != input::Tri::Zero && input.is_just_pressed(Button::B) {
+                    // kick off away from the wall
+                    self.wizard.velocity = (
+                        -sixty_fourths(jump_tuning::WALL_JUMP_VELOCITY) * wall_direction as i32,
+                        -sixty_fourths(jump_tuning::JUMP_VELOCITY),
+                    )
+                        .into();
+                    self.is_jumping = true;
+                    self.jump_frames = 0;
+                    self.facing = match wall_direction {
+                        input::Tri::Positive => input::Tri::Negative,
+                        _ => input::Tri::Positive,
+                    };
+                    sfx_player.jump();
+                } else if is_wall_sliding {
+                    self.wizard.velocity.y = self
+                        .wizard
+                        .velocity
+                        .y
+                        .min(sixty_fourths(jump_tuning::WALL_SLIDE_VELOCITY));
+                }
             }
 
-            self.wizard.velocity = self.wizard.update_position(level);
+            self.wizard.velocity +=
+                level.wind_at(self.wizard.position) / wind_tuning::WIZARD_DIVIDER;
+
+            if self.wizard.velocity.y >= 0.into() {
+                self.is_jumping = false;
+            }
+
+            let height_above_hat = self.height_above_hat();
+            let was_falling = self.wizard.velocity.y > 0.into();
+            self.wizard.velocity = self.wizard.update_position(&level);
+
+            if was_falling && self.wizard.velocity.y < 0.into() {
+                // landed on a bounce tile
+                sfx_player.bounce();
+            }
+
+            if self.hat_is_platform(&level) && self.wizard.velocity.y > 0.into() {
+                if let (Some(before), Some(after)) = (height_above_hat, self.height_above_hat()) {
+                    if before >= 0.into() && after < 0.into() {
+                        // fell onto the top of the hat, so stop there
+                        if self.wizard.velocity.y > 1.into() {
+                            sfx_player.land();
+                        }
+                        self.wizard.position.y += after;
+                        self.wizard.velocity.y = 0.into();
+                    }
+                }
+            }
 
             if self.wizard.velocity.x.abs() > 0.into() {
                 let offset = (ping_pong(timer / 16, 4)) as u16;
@@ -501,10 +1414,20 @@ impl<'a> Player<'a> {
             }
         }
 
-        let hat_base_tile = match self.num_recalls {
+        // the hat looks more worn out as recalls get used up, and is in its final state once
+        // there are none left
+        let hat_tier = if self.num_recalls >= level.rules.max_recalls {
+            2
+        } else if self.num_recalls == 0 {
+            0
+        } else {
+            1
+        };
+
+        let hat_base_tile = match hat_tier {
             0 => object_tiles::HAT_TILE_START,
             1 => object_tiles::HAT_TILE_START_SECOND,
-            2 | _ => object_tiles::HAT_TILE_START_THIRD,
+            _ => object_tiles::HAT_TILE_START_THIRD,
         };
 
         match self.facing {
@@ -519,6 +1442,16 @@ impl<'a> Player<'a> {
             _ => {}
         }
 
+        if let Some(charge) = self.throw_charge {
+            // spin the hat faster and faster as the throw charges up, from a quarter of an
+            // animation frame each frame to a whole one once fully charged
+            self.charge_spin += 4 + charge * 12 / throw_tuning::CHARGE_FRAMES;
+            let spin = (self.charge_spin / 16 + self.facing as i32 * 5).rem_euclid(10);
+            self.hat
+                .sprite
+                .set_tile_id(hat_base_tile + (spin * 4) as u16);
+        }
+
         let hat_resting_position = match self.wizard_frame {
             1 | 2 => (0, 9).into(),
             5 => (0, 10).into(),
@@ -528,19 +1461,13 @@ impl<'a> Player<'a> {
         match self.hat_state {
             HatState::Thrown => {
                 // hat is thrown, make hat move towards wizard
-                let distance_vector =
-                    self.wizard.position - self.hat.position - hat_resting_position;
-                let distance = distance_vector.magnitude();
-                let direction = if distance == 0.into() {
-                    (0, 0).into()
-                } else {
-                    distance_vector / distance
-                };
+                let distance =
+                    (self.wizard.position - self.hat.position - hat_resting_position).magnitude();
 
-                let hat_sprite_divider = match self.num_recalls {
+                let hat_sprite_divider = match hat_tier {
                     0 => 1,
                     1 => 2,
-                    2 | _ => 4,
+                    _ => 4,
                 };
 
                 let hat_sprite_offset = timer / hat_sprite_divider % 10;
@@ -549,18 +1476,22 @@ impl<'a> Player<'a> {
                     .sprite
                     .set_tile_id(hat_base_tile + (hat_sprite_offset * 4) as u16);
 
-                if self.hat_slow_counter < 30 && self.hat.velocity.magnitude() < 2.into() {
-                    self.hat.velocity = (0, 0).into();
-                    self.hat_slow_counter += 1;
-                } else {
-                    self.hat.velocity += direction / 4;
+                self.accelerate_thrown_hat(
+                    &level,
+                    self.wizard.position - hat_resting_position,
+                    is_on_hat,
+                );
+                // the hat smashes through breakable blocks if it is going fast enough
+                if self.hat.velocity.magnitude() >= sixty_fourths(throw_tuning::BREAK_VELOCITY) {
+                    map.break_blocks_ahead(&self.hat);
+                    level = map.current_level();
                 }
-                let (new_velocity, enemy_collision) =
-                    self.hat.update_position_with_enemy(level, enemies);
-                self.hat.velocity = new_velocity;
 
-                if enemy_collision {
-                    sfx_player.snail_hat_bounce();
+                match self.move_thrown_hat(&level, enemies) {
+                    HatCollision::Enemy => sfx_player.snail_hat_bounce(),
+                    HatCollision::Wall => sfx_player.hat_bounce(),
+                    HatCollision::Bounce => sfx_player.bounce(),
+                    HatCollision::None => {}
                 }
 
                 if distance > 16.into() {
@@ -588,7 +1519,10 @@ impl<'a> Player<'a> {
                     let v = self.wizard.velocity.magnitude() + 1;
                     self.wizard.velocity = distance_vector / distance * v;
                 }
-                self.wizard.velocity = self.wizard.update_position(level);
+                // the wizard smashes through breakable blocks while being pulled towards the hat
+                map.break_blocks_ahead(&self.wizard);
+                level = map.current_level();
+                self.wizard.velocity = self.wizard.update_position(&level);
                 if distance < 16.into() {
                     self.wizard.velocity /= 8;
                     self.hat_state = HatState::OnHead;
@@ -596,6 +1530,14 @@ impl<'a> Player<'a> {
                 }
             }
         }
+
+        let is_aiming = input.x_tri() != input::Tri::Zero || input.y_tri() != input::Tri::Zero;
+        self.aim_preview.path =
+            if level.rules.aim_preview && self.hat_state == HatState::OnHead && is_aiming {
+                self.predict_throw(input, &level, enemies, hat_resting_position)
+            } else {
+                [None; AIM_PREVIEW_DOTS]
+            };
     }
 }
 
@@ -604,8 +1546,26 @@ struct PlayingLevel<'a, 'b> {
     background: Map<'a, 'b>,
     input: ButtonController,
     player: Player<'a>,
-
-    enemies: [enemies::Enemy<'a>; 16],
+    object_control: &'a ObjectControl,
+
+    enemies: Vec<enemies::Enemy<'a>>,
+    checkpoints: Vec<checkpoints::Checkpoint<'a>>,
+    active_checkpoint: Option<usize>,
+    collectibles: Vec<Option<collectibles::Collectible<'a>>>, // None once picked up
+    collected: u32,
+    keys: Vec<Option<keys::Key<'a>>>, // None once used to open a door
+    switches: Vec<switches::Switch<'a>>,
+    debris: [Option<debris::Debris<'a>>; 4],
+    wind_particles: [Option<wind::WindParticle<'a>>; 8],
+    projectiles: [Option<projectiles::Projectile<'a>>; 16],
+    springs: Vec<springs::Spring<'a>>,
+    // the two ends of a pair are next to each other, so the other end of n is at n ^ 1
+    teleporters: Vec<teleporters::Teleporter<'a>>,
+    // things which have just come out of a teleporter can't go back in until they've left it
+    wizard_teleported: bool,
+    hat_teleported: bool,
+    snap_camera: bool,
+    water: water::WaterOverlay,
 }
 
 enum UpdateState {
@@ -616,48 +1576,198 @@ enum UpdateState {
 
 impl<'a, 'b, 'c> PlayingLevel<'a, 'b> {
     fn open_level(
-        level: &'a Level,
+        level: &'a Level<'static>,
         object_control: &'a ObjectControl,
         background: &'a mut BackgroundRegular<'b>,
         foreground: &'a mut BackgroundRegular<'b>,
         input: ButtonController,
     ) -> Self {
-        let mut e: [enemies::Enemy<'a>; 16] = Default::default();
-        let mut enemy_count = 0;
-        for &slime in level.slimes {
-            e[enemy_count] = enemies::Enemy::new_slime(object_control, slime.into());
-            enemy_count += 1;
-        }
-
-        for &snail in level.snails {
-            e[enemy_count] = enemies::Enemy::new_snail(object_control, snail.into());
-            enemy_count += 1;
-        }
+        let checkpoints = level
+            .checkpoints
+            .iter()
+            .map(|&position| checkpoints::Checkpoint::new(object_control, position.into()))
+            .collect();
 
         let start_pos: Vector2D<FixedNumberType> = level.start_pos.into();
 
-        let background_position = (
-            (start_pos.x - WIDTH / 2)
-                .clamp(0.into(), ((level.dimensions.x * 8) as i32 - WIDTH).into()),
-            (start_pos.y - HEIGHT / 2)
-                .clamp(0.into(), ((level.dimensions.y * 8) as i32 - HEIGHT).into()),
-        )
-            .into();
-
         PlayingLevel {
             timer: 0,
             background: Map {
                 background,
                 foreground,
-                level,
-                position: background_position,
+                level: level.clone(),
+                position: Self::map_position_centred_on(level, start_pos),
+                blocks_toggled: false,
+                broken_blocks: Vec::new(),
             },
             player: Player::new(object_control, start_pos),
+            object_control,
             input,
-            enemies: e,
+            enemies: Self::spawn_enemies(level, object_control),
+            checkpoints,
+            active_checkpoint: None,
+            collectibles: Self::spawn_collectibles(level, object_control),
+            collected: 0,
+            keys: Self::spawn_keys(level, object_control),
+            switches: Self::spawn_switches(level, object_control),
+            debris: Default::default(),
+            wind_particles: Default::default(),
+            projectiles: Default::default(),
+            springs: Self::spawn_springs(level, object_control),
+            teleporters: Self::spawn_teleporters(level, object_control),
+            wizard_teleported: false,
+            hat_teleported: false,
+            snap_camera: false,
+            water: water::WaterOverlay::new(),
         }
     }
 
+    fn has_water(&self) -> bool {
+        !self.background.level.water.is_empty()
+    }
+
+    fn commit_water(&mut self, overlay: &mut BackgroundRegister) {
+        if self.has_water() {
+            self.water
+                .commit(overlay, &self.background.level, self.background.position);
+        }
+    }
+
+    fn spawn_springs(level: &Level, object_control: &'a ObjectControl) -> Vec<springs::Spring<'a>> {
+        level
+            .springs
+            .iter()
+            .map(|&position| springs::Spring::new(object_control, position.into()))
+            .collect()
+    }
+
+    fn spawn_teleporters(
+        level: &Level,
+        object_control: &'a ObjectControl,
+    ) -> Vec<teleporters::Teleporter<'a>> {
+        level
+            .teleporters
+            .iter()
+            .flat_map(|&(from, to)| [from, to])
+            .map(|position| teleporters::Teleporter::new(object_control, position.into()))
+            .collect()
+    }
+
+    fn spawn_switches(
+        level: &Level,
+        object_control: &'a ObjectControl,
+    ) -> Vec<switches::Switch<'a>> {
+        level
+            .switches
+            .iter()
+            .map(|&position| switches::Switch::new(object_control, position.into()))
+            .collect()
+    }
+
+    fn spawn_keys(level: &Level, object_control: &'a ObjectControl) -> Vec<Option<keys::Key<'a>>> {
+        level
+            .keys
+            .iter()
+            .map(|&position| Some(keys::Key::new(object_control, position.into())))
+            .collect()
+    }
+
+    fn spawn_collectibles(
+        level: &Level,
+        object_control: &'a ObjectControl,
+    ) -> Vec<Option<collectibles::Collectible<'a>>> {
+        level
+            .collectibles
+            .iter()
+            .map(|&position| {
+                Some(collectibles::Collectible::new(
+                    object_control,
+                    position.into(),
+                ))
+            })
+            .collect()
+    }
+
+    // how many of the collectibles in this level have been picked up, and how many there are
+    fn collectible_count(&self) -> (u32, u32) {
+        (self.collected, self.collectibles.len() as u32)
+    }
+
+    fn spawn_enemies(level: &Level, object_control: &'a ObjectControl) -> Vec<enemies::Enemy<'a>> {
+        level
+            .enemies
+            .iter()
+            .map(|spawn| enemies::spawn(object_control, spawn))
+            .collect()
+    }
+
+    fn map_position_centred_on(
+        level: &Level,
+        position: Vector2D<FixedNumberType>,
+    ) -> Vector2D<FixedNumberType> {
+        (
+            (position.x - WIDTH / 2)
+                .clamp(0.into(), ((level.dimensions.x * 8) as i32 - WIDTH).into()),
+            (position.y - HEIGHT / 2)
+                .clamp(0.into(), ((level.dimensions.y * 8) as i32 - HEIGHT).into()),
+        )
+            .into()
+    }
+
+    // puts the player back at the start (or the active checkpoint) and respawns the enemies,
+    // keeping the backgrounds which are already loaded
+    fn restart(&mut self) {
+        let level = &self.background.level;
+        let start_pos = self
+            .active_checkpoint
+            .map(|index| self.checkpoints[index].position())
+            .unwrap_or_else(|| level.start_pos.into());
+
+        self.timer = 0;
+        self.player.restart(start_pos);
+
+        // the old sprites are freed before spawning new ones, so there are never two sets at once
+        self.enemies.clear();
+        self.enemies = Self::spawn_enemies(level, self.object_control);
+
+        self.switches.clear();
+        self.switches = Self::spawn_switches(level, self.object_control);
+        self.debris = Default::default();
+        self.wind_particles = Default::default();
+        self.projectiles = Default::default();
+        self.wizard_teleported = false;
+        self.hat_teleported = false;
+
+        // anything collected before reaching a checkpoint is kept, as are any doors opened
+        // and switches flipped
+        if self.active_checkpoint.is_none() {
+            self.collectibles.clear();
+            self.collectibles = Self::spawn_collectibles(level, self.object_control);
+            self.collected = 0;
+            self.keys.clear();
+            self.keys = Self::spawn_keys(level, self.object_control);
+        } else {
+            // keys which haven't been used yet go back to where they started
+            for (key, &position) in self.keys.iter_mut().zip(level.keys) {
+                if let Some(key) = key {
+                    key.restart(position.into());
+                }
+            }
+        }
+
+        for checkpoint in self.checkpoints.iter_mut() {
+            checkpoint.restart();
+        }
+
+        self.background.position = Self::map_position_centred_on(level, start_pos);
+
+        if self.active_checkpoint.is_none() {
+            self.background.reset_foreground();
+        }
+
+        self.background.commit_position();
+    }
+
     fn load_1(&'c mut self) -> impl Iterator<Item = ()> + 'c {
         self.background.load_background()
     }
@@ -700,51 +1810,359 @@ impl<'a, 'b, 'c> PlayingLevel<'a, 'b> {
         self.player.update_frame(
             &self.input,
             self.timer,
-            &self.background.level,
+            &mut self.background,
             &self.enemies,
             sfx_player,
         );
+        self.spawn_debris(sfx_player);
 
-        for enemy in self.enemies.iter_mut() {
-            match enemy.update(
-                &self.background.level,
+        self.teleport(sfx_player);
+
+        let level = self.background.current_level();
+        let mut i = 0;
+        while i < self.enemies.len() {
+            match self.enemies[i].update(
+                &level,
                 self.player.wizard.position,
                 self.player.hat_state,
                 self.timer,
                 sfx_player,
             ) {
-                enemies::EnemyUpdateState::KillPlayer => player_dead = true,
-                enemies::EnemyUpdateState::None => {}
+                enemies::UpdateState::Remove => {
+                    self.enemies.swap_remove(i);
+                    continue;
+                }
+                enemies::UpdateState::KillPlayer => player_dead = true,
+                enemies::UpdateState::Fire(position, velocity) => {
+                    if let Some(slot) = self.projectiles.iter_mut().find(|p| p.is_none()) {
+                        *slot = Some(projectiles::Projectile::new(
+                            self.object_control,
+                            position,
+                            velocity,
+                        ));
+                    }
+                }
+                enemies::UpdateState::Nothing => {}
+            }
+            i += 1;
+        }
+
+        player_dead |= self.update_projectiles(sfx_player);
+
+        for i in 0..self.checkpoints.len() {
+            let activated =
+                self.checkpoints[i].update(self.player.wizard.position, self.timer, sfx_player);
+
+            if activated {
+                if let Some(previous) = self.active_checkpoint {
+                    self.checkpoints[previous].deactivate();
+                }
+                self.active_checkpoint = Some(i);
+            }
+        }
+
+        for collectible in self.collectibles.iter_mut() {
+            let picked_up = match collectible {
+                Some(collectible) => collectible.update(
+                    self.player.wizard.position,
+                    self.player.hat.position,
+                    self.timer,
+                    sfx_player,
+                ),
+                None => false,
+            };
+
+            if picked_up {
+                *collectible = None;
+                self.collected += 1;
+            }
+        }
+
+        for key in self.keys.iter_mut().flatten() {
+            key.update(
+                self.player.wizard.position,
+                self.player.hat.position,
+                self.player.hat_state,
+                sfx_player,
+            );
+        }
+
+        self.open_doors(sfx_player);
+
+        for spring in self.springs.iter_mut() {
+            let player = &mut self.player;
+            if spring.launches(&player.wizard) {
+                player.wizard.velocity.y = -sixty_fourths(jump_tuning::SPRING_VELOCITY);
+                player.is_jumping = false;
+                spring.press();
+                sfx_player.bounce();
+            }
+
+            if player.hat_state == HatState::Thrown && spring.reflects(&player.hat) {
+                player.hat.velocity *= -1;
+                spring.press();
+                sfx_player.bounce();
             }
+
+            spring.update();
+        }
+
+        let mut switch_hit = false;
+        for switch in self.switches.iter_mut() {
+            switch_hit |= switch.update(
+                self.player.hat.position,
+                self.player.hat_state,
+                self.background.blocks_toggled,
+            );
+        }
+
+        if switch_hit {
+            self.background.toggle_blocks();
+            sfx_player.flip_switch();
         }
 
         self.background.position = self.get_next_map_position();
+        self.snap_camera = false;
         self.background.commit_position();
 
         self.player.wizard.commit_position(self.background.position);
         self.player.hat.commit_position(self.background.position);
+        self.player.aim_preview.commit(self.background.position);
 
         for enemy in self.enemies.iter_mut() {
             enemy.commit(self.background.position);
         }
 
-        player_dead |= self
-            .player
-            .wizard
-            .killision_at_point(&self.background.level, self.player.wizard.position);
+        for checkpoint in self.checkpoints.iter_mut() {
+            checkpoint.commit(self.background.position);
+        }
+
+        for collectible in self.collectibles.iter_mut().flatten() {
+            collectible.commit(self.background.position);
+        }
+
+        for key in self.keys.iter_mut().flatten() {
+            key.commit(self.background.position);
+        }
+
+        for switch in self.switches.iter_mut() {
+            switch.commit(self.background.position);
+        }
+
+        for spring in self.springs.iter_mut() {
+            spring.commit(self.background.position);
+        }
+
+        for teleporter in self.teleporters.iter_mut() {
+            teleporter.update(self.timer);
+            teleporter.commit(self.background.position);
+        }
+
+        for projectile in self.projectiles.iter_mut().flatten() {
+            projectile.commit(self.background.position);
+        }
+
+        self.spawn_wind_particle();
+        for particle in self.wind_particles.iter_mut() {
+            let finished = match particle {
+                Some(particle) => !particle.update(&self.background.level),
+                None => false,
+            };
+
+            if finished {
+                *particle = None;
+            } else if let Some(particle) = particle {
+                particle.commit(self.background.position);
+            }
+        }
+
+        for debris in self.debris.iter_mut() {
+            let finished = match debris {
+                Some(debris) => !debris.update(self.timer),
+                None => false,
+            };
+
+            if finished {
+                *debris = None;
+            } else if let Some(debris) = debris {
+                debris.commit(self.background.position);
+            }
+        }
+
+        let level = self.background.current_level();
+        let wizard = &self.player.wizard;
+        player_dead |= wizard.killision_at_point(&level, wizard.position);
         if player_dead {
             UpdateState::Dead
-        } else if self
-            .player
-            .wizard
-            .completion_at_point(self.background.level, self.player.wizard.position)
-        {
+        } else if wizard.completion_at_point(&level, wizard.position) {
             UpdateState::Complete
         } else {
             UpdateState::Normal
         }
     }
 
+    // returns true if the wizard was hit
+    fn update_projectiles(&mut self, sfx_player: &mut sfx::SfxPlayer) -> bool {
+        let player = &self.player;
+        let level = self.background.current_level();
+        let mut hit_player = false;
+
+        for slot in self.projectiles.iter_mut() {
+            let projectile = match slot {
+                Some(projectile) => projectile,
+                None => continue,
+            };
+
+            if !projectile.update(&level) {
+                *slot = None;
+                continue;
+            }
+
+            // the thrown hat knocks back projectiles while it's flying, and stops them dead while
+            // it's hovering
+            if player.hat_state == HatState::Thrown && projectile.touches(player.hat.position, 8) {
+                if player.hat.velocity.magnitude() < 1.into() {
+                    *slot = None;
+                    sfx_player.hat_bounce();
+                    continue;
+                } else if projectile.reflect_from(player.hat.position) {
+                    sfx_player.hat_bounce();
+                }
+            }
+
+            hit_player |= projectile.touches(player.wizard.position, 6);
+        }
+
+        hit_player
+    }
+
+    // every so often a particle is blown into view in one of the wind zones
+    fn spawn_wind_particle(&mut self) {
+        let wind = self.background.level.wind;
+        if wind.is_empty() || self.timer % wind_tuning::PARTICLE_SPAWN_FRAMES != 0 {
+            return;
+        }
+
+        let slot = match self.wind_particles.iter_mut().find(|p| p.is_none()) {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        // a cheap hash of the timer is random enough to scatter the particles around
+        let spawn_count = self.timer / wind_tuning::PARTICLE_SPAWN_FRAMES;
+        let hash = (spawn_count as u32).wrapping_mul(2_654_435_761);
+        let ((x, y, width, height), (force_x, force_y)) = wind[spawn_count as usize % wind.len()];
+        let position: Vector2D<FixedNumberType> = (
+            x + (hash % width.max(1) as u32) as i32,
+            y + ((hash >> 16) % height.max(1) as u32) as i32,
+        )
+            .into();
+
+        let on_screen = position - self.background.position;
+        if on_screen.x < 0.into()
+            || on_screen.y < 0.into()
+            || on_screen.x >= WIDTH.into()
+            || on_screen.y >= HEIGHT.into()
+        {
+            return;
+        }
+
+        let velocity: Vector2D<FixedNumberType> =
+            (sixty_fourths(force_x), sixty_fourths(force_y)).into();
+        *slot = Some(wind::WindParticle::new(
+            self.object_control,
+            position,
+            velocity * wind_tuning::PARTICLE_SPEED,
+        ));
+    }
+
+    // blocks are broken while the player moves, and break apart into debris afterwards
+    fn spawn_debris(&mut self, sfx_player: &mut sfx::SfxPlayer) {
+        for (x, y) in self.background.broken_blocks.drain(..) {
+            if let Some(slot) = self.debris.iter_mut().find(|debris| debris.is_none()) {
+                let position = (x * 8 + 4, y * 8 + 4).into();
+                *slot = Some(debris::Debris::new(
+                    self.object_control,
+                    position,
+                    self.timer,
+                ));
+            }
+            sfx_player.break_block();
+        }
+    }
+
+    // the other end of the teleporter at this position, if there is one
+    fn teleport_destination(
+        &self,
+        position: Vector2D<FixedNumberType>,
+    ) -> Option<Vector2D<FixedNumberType>> {
+        let entrance = self
+            .teleporters
+            .iter()
+            .position(|teleporter| teleporter.touches(position))?;
+
+        Some(self.teleporters[entrance ^ 1].position())
+    }
+
+    // things keep their velocity when going through a teleporter, so they come out moving the
+    // same way they went in
+    fn teleport(&mut self, sfx_player: &mut sfx::SfxPlayer) {
+        match self.teleport_destination(self.player.wizard.position) {
+            Some(destination) if !self.wizard_teleported => {
+                let offset = destination - self.player.wizard.position;
+                self.player.wizard.position += offset;
+                if self.player.hat_state == HatState::OnHead {
+                    self.player.hat.position += offset;
+                }
+
+                self.wizard_teleported = true;
+                self.snap_camera = true;
+                sfx_player.teleport();
+            }
+            Some(_) => {}
+            None => self.wizard_teleported = false,
+        }
+
+        if !self.background.level.rules.hat_teleports || self.player.hat_state != HatState::Thrown {
+            return;
+        }
+
+        match self.teleport_destination(self.player.hat.position) {
+            Some(destination) if !self.hat_teleported => {
+                self.player.hat.position = destination;
+                self.hat_teleported = true;
+                sfx_player.teleport();
+            }
+            Some(_) => {}
+            None => self.hat_teleported = false,
+        }
+    }
+
+    // the wizard opens a door by walking into it while they have a key
+    fn open_doors(&mut self, sfx_player: &mut sfx::SfxPlayer) {
+        let hat_state = self.player.hat_state;
+        let key = match self
+            .keys
+            .iter_mut()
+            .find(|key| matches!(key, Some(key) if key.is_held(hat_state)))
+        {
+            Some(key) => key,
+            None => return,
+        };
+
+        let wizard = &self.player.wizard;
+        let level = self.background.current_level();
+        let door = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .find_map(|&offset| wizard.door_at_point(&level, wizard.position + offset.into()));
+
+        if let Some((x, y)) = door {
+            *key = None;
+            self.background.open_door(x, y);
+            sfx_player.open_door();
+        }
+    }
+
     fn get_next_map_position(&self) -> Vector2D<FixedNumberType> {
         // want to ensure the player and the hat are visible if possible, so try to position the map
         // so the centre is at the average position. But give the player some extra priority
@@ -757,7 +2175,12 @@ impl<'a, 'b, 'c> PlayingLevel<'a, 'b> {
         let half_screen = screen / 2;
         let current_centre = self.background.position.floor() + half_screen;
 
-        let mut target_position = ((current_centre * 3 + new_target_position) / 4) - half_screen;
+        // after teleporting the camera jumps straight there rather than sweeping across the level
+        let mut target_position = if self.snap_camera {
+            new_target_position - half_screen
+        } else {
+            ((current_centre * 3 + new_target_position) / 4) - half_screen
+        };
 
         target_position.x = target_position.x.clamp(
             0.into(),
@@ -788,11 +2211,19 @@ pub fn main() -> ! {
         object.set_sprite_palettes(object_sheet::object_sheet.palettes);
         object.set_sprite_tilemap(object_sheet::object_sheet.tiles);
 
+        // kept on the heap as it is too big for the stack
+        let mut world_tiles = alloc::vec![0; map_tiles::MAX_LEVEL_SIZE];
+
         let mut world_display = tiled.get_raw_regular().unwrap();
         world_display.clear(level_display::BLANK);
         world_display.show();
 
         let mut background = tiled.get_regular().unwrap();
+        background.set_map(agb::display::background::Map::new_mutable(
+            &mut world_tiles,
+            (0u32, 0u32).into(),
+            0,
+        ));
         let mut foreground = tiled.get_regular().unwrap();
         object.enable();
 
@@ -811,6 +2242,7 @@ pub fn main() -> ! {
             music_box.after_blank(&mut mixer);
             mixer.vblank();
 
+            world_display.clear(level_display::BLANK);
             level_display::write_level(
                 &mut world_display,
                 current_level / 8 + 1,
@@ -850,8 +2282,20 @@ pub fn main() -> ! {
 
             world_display.hide();
 
+            // while playing, the world display is reused to draw water over the level
+            if level.has_water() {
+                world_display.clear(map_tiles::tilemap::BLANK_TILE);
+                level.commit_water(&mut world_display);
+                water::enable_blending();
+                world_display.show();
+            }
+
             loop {
-                match level.update_frame(&mut sfx::SfxPlayer::new(&mut mixer, &music_box)) {
+                let update_state =
+                    level.update_frame(&mut sfx::SfxPlayer::new(&mut mixer, &music_box));
+                level.commit_water(&mut world_display);
+
+                match update_state {
                     UpdateState::Normal => {}
                     UpdateState::Dead => {
                         level.dead_start();
@@ -860,9 +2304,28 @@ pub fn main() -> ! {
                             music_box.after_blank(&mut mixer);
                             mixer.vblank();
                         }
-                        break;
+
+                        level.restart();
                     }
                     UpdateState::Complete => {
+                        // every way out of the level comes through here, so this is where the
+                        // water stops being blended
+                        water::disable_blending();
+                        world_display.hide();
+
+                        let (collected, total) = level.collectible_count();
+                        if total > 0 {
+                            world_display.clear(level_display::BLANK);
+                            level_display::write_collectibles(&mut world_display, collected, total);
+                            world_display.show();
+
+                            for _ in 0..90 {
+                                vblank.wait_for_vblank();
+                                music_box.after_blank(&mut mixer);
+                                mixer.vblank();
+                            }
+                        }
+
                         current_level += 1;
                         break;
                     }
@@ -0,0 +1,57 @@
+use super::{object_tiles, Entity, FixedNumberType, Level};
+use agb::{
+    display::object::{ObjectControl, Size},
+    number::Vector2D,
+};
+
+// projectiles which somehow never hit anything are removed after this long
+const LIFETIME: i32 = 240;
+
+// a shot fired by a turret, which kills the wizard if it hits them
+pub struct Projectile<'a> {
+    entity: Entity<'a>,
+    age: i32,
+}
+
+impl<'a> Projectile<'a> {
+    pub fn new(
+        object: &'a ObjectControl,
+        position: Vector2D<FixedNumberType>,
+        velocity: Vector2D<FixedNumberType>,
+    ) -> Self {
+        let mut entity = Entity::new(object, (4u16, 4u16).into());
+        entity.position = position;
+        entity.velocity = velocity;
+        entity.sprite.set_sprite_size(Size::S16x16);
+        entity.sprite.set_tile_id(object_tiles::PROJECTILE);
+
+        Projectile { entity, age: 0 }
+    }
+
+    pub fn touches(&self, position: Vector2D<FixedNumberType>, distance: i32) -> bool {
+        self.entity.is_within(position, distance)
+    }
+
+    // the hat knocks projectiles back the way they came, as long as they're heading towards it
+    pub fn reflect_from(&mut self, position: Vector2D<FixedNumberType>) -> bool {
+        let offset = position - self.entity.position;
+        if offset.x * self.entity.velocity.x + offset.y * self.entity.velocity.y > 0.into() {
+            self.entity.velocity *= -1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // returns false once the projectile has hit a wall
+    pub fn update(&mut self, level: &Level) -> bool {
+        self.age += 1;
+        self.entity.position += self.entity.velocity;
+
+        self.age < LIFETIME && !self.entity.collision_at_point(level, self.entity.position)
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
@@ -109,11 +109,68 @@ impl<'a> SfxPlayer<'a> {
             .play_sound(SoundChannel::new(effects::SNAIL_HAT_BOUNCE));
     }
 
+    // the hat sounds the same bouncing off a wall as it does off a snail
+    pub fn hat_bounce(&mut self) {
+        self.snail_hat_bounce();
+    }
+
     pub fn snail_death(&mut self) {
         self.mixer
             .play_sound(SoundChannel::new(effects::SNAIL_DEATH));
     }
 
+    pub fn bat_dive(&mut self) {
+        self.play_random(effects::WHOOSHES);
+    }
+
+    pub fn bat_death(&mut self) {
+        self.mixer
+            .play_sound(SoundChannel::new(effects::SLIME_DEATH));
+    }
+
+    pub fn turret_death(&mut self) {
+        self.break_block();
+    }
+
+    pub fn fire(&mut self) {
+        self.play_random(effects::WHOOSHES);
+    }
+
+    pub fn collect(&mut self) {
+        self.catch();
+    }
+
+    pub fn open_door(&mut self) {
+        self.mixer.play_sound(SoundChannel::new(effects::LAND));
+    }
+
+    pub fn flip_switch(&mut self) {
+        self.mixer
+            .play_sound(SoundChannel::new(effects::SNAIL_HAT_BOUNCE));
+    }
+
+    pub fn break_block(&mut self) {
+        self.mixer
+            .play_sound(SoundChannel::new(effects::SLIME_DEATH));
+    }
+
+    pub fn bounce(&mut self) {
+        self.mixer
+            .play_sound(SoundChannel::new(effects::SLIME_JUMP));
+    }
+
+    pub fn swim(&mut self) {
+        self.play_random(effects::WHOOSHES);
+    }
+
+    pub fn teleport(&mut self) {
+        self.play_random(effects::WHOOSHES);
+    }
+
+    pub fn checkpoint(&mut self) {
+        self.collect();
+    }
+
     pub fn land(&mut self) {
         self.mixer.play_sound(SoundChannel::new(effects::LAND));
     }
@@ -0,0 +1,54 @@
+use super::{object_tiles, Entity, FixedNumberType};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+// how long the spring stays squashed after launching something
+const PRESSED_FRAMES: i32 = 8;
+
+pub struct Spring<'a> {
+    entity: Entity<'a>,
+    pressed_frames: i32,
+}
+
+impl<'a> Spring<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::SPRING);
+
+        Spring {
+            entity,
+            pressed_frames: 0,
+        }
+    }
+
+    // the wizard is launched if they fall onto the top of the spring
+    pub fn launches(&self, wizard: &Entity) -> bool {
+        let offset = self.entity.position - wizard.position;
+        wizard.velocity.y > 0.into()
+            && offset.x.abs() < 10.into()
+            && offset.y >= 0.into()
+            && offset.y < 16.into()
+    }
+
+    // the hat bounces back the way it came if it hits the spring while moving towards it
+    pub fn reflects(&self, hat: &Entity) -> bool {
+        let offset = self.entity.position - hat.position;
+        offset.magnitude_squared() < (12 * 12).into()
+            && offset.x * hat.velocity.x + offset.y * hat.velocity.y > 0.into()
+    }
+
+    pub fn press(&mut self) {
+        self.pressed_frames = PRESSED_FRAMES;
+    }
+
+    pub fn update(&mut self) {
+        if self.pressed_frames > 0 {
+            self.pressed_frames -= 1;
+            self.entity.sprite.set_tile_id(object_tiles::SPRING_PRESSED);
+        } else {
+            self.entity.sprite.set_tile_id(object_tiles::SPRING);
+        }
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
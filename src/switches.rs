@@ -0,0 +1,42 @@
+use super::{object_tiles, Entity, FixedNumberType, HatState};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+pub struct Switch<'a> {
+    entity: Entity<'a>,
+    hat_touching: bool,
+}
+
+impl<'a> Switch<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::SWITCH_OFF);
+
+        Switch {
+            entity,
+            hat_touching: false,
+        }
+    }
+
+    // returns true if the thrown hat has just hit this switch. The hat has to leave the
+    // switch again before it can flip it back.
+    pub fn update(
+        &mut self,
+        hat_pos: Vector2D<FixedNumberType>,
+        hat_state: HatState,
+        toggled: bool,
+    ) -> bool {
+        self.entity.sprite.set_tile_id(if toggled {
+            object_tiles::SWITCH_ON
+        } else {
+            object_tiles::SWITCH_OFF
+        });
+
+        let was_touching = self.hat_touching;
+        self.hat_touching = hat_state != HatState::OnHead && self.entity.is_within(hat_pos, 10);
+
+        self.hat_touching && !was_touching
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
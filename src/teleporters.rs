@@ -0,0 +1,37 @@
+use super::{object_tiles, Entity, FixedNumberType};
+use agb::{display::object::ObjectControl, number::Vector2D};
+
+const FRAMES: i32 = 2;
+const FRAME_LENGTH: i32 = 8;
+
+// One end of a pair of teleporters. Anything going in comes out of the other end of the pair.
+pub struct Teleporter<'a> {
+    entity: Entity<'a>,
+}
+
+impl<'a> Teleporter<'a> {
+    pub fn new(object: &'a ObjectControl, position: Vector2D<FixedNumberType>) -> Self {
+        let entity = Entity::new_at(object, position, object_tiles::TELEPORTER_START);
+
+        Teleporter { entity }
+    }
+
+    pub fn position(&self) -> Vector2D<FixedNumberType> {
+        self.entity.position
+    }
+
+    pub fn touches(&self, position: Vector2D<FixedNumberType>) -> bool {
+        self.entity.is_within(position, 8)
+    }
+
+    pub fn update(&mut self, timer: i32) {
+        let frame = timer / FRAME_LENGTH % FRAMES;
+        self.entity
+            .sprite
+            .set_tile_id(object_tiles::TELEPORTER_START + frame as u16 * 4);
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}
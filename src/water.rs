@@ -0,0 +1,72 @@
+use super::{map_tiles, FixedNumberType, Level};
+use agb::{display::background::BackgroundRegister, number::Vector2D};
+
+// agb doesn't support blending yet, so the registers are written directly
+const BLEND_CONTROL: *mut u16 = 0x0400_0050 as *mut u16;
+const BLEND_ALPHA: *mut u16 = 0x0400_0052 as *mut u16;
+
+const WATER_TILE: u16 = 14 * 28 + 13;
+const WATER_SURFACE_TILE: u16 = 14 * 28 + 14;
+
+// The overlay is the first background (BG0), and is blended on top of the other backgrounds,
+// the sprites and the backdrop.
+pub fn enable_blending() {
+    let first_target = 1 << 0;
+    let alpha_blending = 1 << 6;
+    let second_target = 0b111110 << 8; // BG1-3, sprites and the backdrop
+    let (overlay_weight, below_weight) = (7, 10); // out of 16
+
+    unsafe {
+        BLEND_CONTROL.write_volatile(first_target | alpha_blending | second_target);
+        BLEND_ALPHA.write_volatile(overlay_weight | below_weight << 8);
+    }
+}
+
+pub fn disable_blending() {
+    unsafe {
+        BLEND_CONTROL.write_volatile(0);
+    }
+}
+
+// Draws the water in the level into a background which is blended on top of everything else,
+// so things in the water are tinted by it.
+pub struct WaterOverlay {
+    drawn_at: Option<Vector2D<i32>>,
+}
+
+impl WaterOverlay {
+    pub fn new() -> Self {
+        WaterOverlay { drawn_at: None }
+    }
+
+    pub fn commit(
+        &mut self,
+        overlay: &mut BackgroundRegister,
+        level: &Level,
+        position: Vector2D<FixedNumberType>,
+    ) {
+        let position = position.floor();
+        overlay.set_position(position);
+
+        // only the tiles on screen are drawn, so it only needs redrawing after moving a tile
+        let tile_position = position / 8;
+        if self.drawn_at == Some(tile_position) {
+            return;
+        }
+        self.drawn_at = Some(tile_position);
+
+        let block = overlay.get_block();
+        for y in tile_position.y..tile_position.y + 21 {
+            for x in tile_position.x..tile_position.x + 31 {
+                block[y.rem_euclid(32) as usize][x.rem_euclid(32) as usize] =
+                    if !level.water_at_tile(x, y) {
+                        map_tiles::tilemap::BLANK_TILE
+                    } else if level.water_at_tile(x, y - 1) {
+                        WATER_TILE
+                    } else {
+                        WATER_SURFACE_TILE
+                    };
+            }
+        }
+    }
+}
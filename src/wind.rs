@@ -0,0 +1,41 @@
+use super::{object_tiles, Entity, FixedNumberType, Level};
+use agb::{
+    display::object::{ObjectControl, Size},
+    number::Vector2D,
+};
+
+const LIFETIME: i32 = 24;
+
+// a wisp blown along by the wind, to show which way the wind is going
+pub struct WindParticle<'a> {
+    entity: Entity<'a>,
+    age: i32,
+}
+
+impl<'a> WindParticle<'a> {
+    pub fn new(
+        object: &'a ObjectControl,
+        position: Vector2D<FixedNumberType>,
+        velocity: Vector2D<FixedNumberType>,
+    ) -> Self {
+        let mut entity = Entity::new(object, (8u16, 8u16).into());
+        entity.position = position;
+        entity.velocity = velocity;
+        entity.sprite.set_sprite_size(Size::S16x16);
+        entity.sprite.set_tile_id(object_tiles::WIND_PARTICLE);
+
+        WindParticle { entity, age: 0 }
+    }
+
+    // returns false once the particle has blown away or left the wind
+    pub fn update(&mut self, level: &Level) -> bool {
+        self.age += 1;
+        self.entity.position += self.entity.velocity;
+
+        self.age < LIFETIME && level.in_wind(self.entity.position)
+    }
+
+    pub fn commit(&mut self, background_offset: Vector2D<FixedNumberType>) {
+        self.entity.commit_position(background_offset);
+    }
+}